@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// A large document with a mix of inline markup, lists and tables.
+fn large_document() -> String {
+    let mut html = String::from("<html><body>");
+    for i in 0..500 {
+        html.push_str(&format!(
+            "<h2>Section {}</h2>\
+             <p>Some <em>emphasised</em>, <strong>strong</strong> and \
+             <a href=\"#s{}\">linked</a> text.</p>\
+             <ul><li>one</li><li>two <code>code</code></li></ul>\
+             <dl><dt>term</dt><dd>definition</dd></dl>\
+             <table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td></tr></table>",
+            i, i
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// A document made of preformatted blocks full of inline markup.
+fn preformatted_document() -> String {
+    let line = "<span>fn <b>main</b>() {<i>\t<em>body</em></i>}</span>\n";
+    let mut html = String::from("<html><body>");
+    for _ in 0..100 {
+        html.push_str("<pre>");
+        html.push_str(&line.repeat(50));
+        html.push_str("</pre><div style=\"white-space: pre-wrap\">");
+        html.push_str(&line.repeat(50));
+        html.push_str("</div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// The length and FNV-1a hash of `text`, to compare output against that
+/// of earlier versions without storing it all.
+fn fingerprint(text: &str) -> (usize, u64) {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    (text.len(), hash)
+}
+
+fn parse(c: &mut Criterion) {
+    let html = large_document();
+    // Changes made for speed shouldn't change the output.
+    assert_eq!(
+        fingerprint(&html2text::from_read(html.as_bytes(), 80)),
+        (83672, 0x20a0_9cb9_9298_42c9)
+    );
+    c.bench_function("parse large document", |b| {
+        b.iter(|| html2text::parse(html.as_bytes()))
+    });
+}
+
+fn preformatted(c: &mut Criterion) {
+    let html = preformatted_document();
+    assert_eq!(
+        fingerprint(&html2text::from_read(html.as_bytes(), 80)),
+        (240000, 0xc704_afc1_8e1b_65a5)
+    );
+    c.bench_function("convert preformatted text", |b| {
+        b.iter(|| html2text::from_read(html.as_bytes(), 80))
+    });
+}
+
+fn deeply_nested(c: &mut Criterion) {
+    let depth = 50_000;
+    let html = format!("{}deep{}", "<div>".repeat(depth), "</div>".repeat(depth));
+    let mut group = c.benchmark_group("deeply nested");
+    group.sample_size(10);
+    group.bench_function("parse 50k nested divs", |b| {
+        b.iter(|| html2text::parse(html.as_bytes()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, preformatted, deeply_nested);
+criterion_main!(benches);
@@ -26,7 +26,7 @@ mod top {
 
         for ann in tag {
             match *ann {
-                RichAnnotation::Default => (),
+                RichAnnotation::Default | RichAnnotation::Element(_) => (),
                 RichAnnotation::Link(_) => {
                     style.push_str(&format!("{}", termion::style::Underline));
                 }
@@ -49,6 +49,12 @@ mod top {
                     ));
                 }
                 RichAnnotation::Strikeout => (),
+                RichAnnotation::Underline => {
+                    style.push_str(&format!("{}", termion::style::Underline));
+                }
+                RichAnnotation::Small => {
+                    style.push_str(&format!("{}", termion::style::Faint));
+                }
                 RichAnnotation::Code => {
                     style.push_str(&format!(
                         "{}",
@@ -68,6 +74,11 @@ mod top {
                 RichAnnotation::Colored(c) => {
                     style.push_str(&format!("{}",termion::color::Fg(termion::color::AnsiValue::rgb(c.r,c.g,c.b))))
                 },
+                RichAnnotation::BgColored(c) => {
+                    // The 6x6x6 colour cube takes components from 0 to 5.
+                    let bg = termion::color::AnsiValue::rgb(c.r / 51, c.g / 51, c.b / 51);
+                    style.push_str(&format!("{}", termion::color::Bg(bg)))
+                },
                 RichAnnotation::Bell => {
                     style.push_str(&format!("bell!"))
                 }
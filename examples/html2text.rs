@@ -46,7 +46,7 @@ pub fn default_colour_map(
     use termion::color::*;
     use RichAnnotation::*;
     match annotation {
-        Default => ("".into(), Box::new(|s| s.to_string()), "".into()),
+        Default | Element(_) => ("".into(), Box::new(|s| s.to_string()), "".into()),
         Link(_) => (
             format!("{}", termion::style::Underline),
             Box::new(|s| s.to_string()),
@@ -72,6 +72,16 @@ pub fn default_colour_map(
             Box::new(|s| s.to_string()),
             format!("{}", Fg(Reset)),
         ),
+        Underline => (
+            format!("{}", termion::style::Underline),
+            Box::new(|s| s.to_string()),
+            format!("{}", termion::style::Reset),
+        ),
+        Small => (
+            format!("{}", Fg(LightBlack)),
+            Box::new(|s| s.to_string()),
+            format!("{}", Fg(Reset)),
+        ),
         Code => (
             format!("{}", Fg(Blue)),
             Box::new(|s| s.to_string()),
@@ -90,6 +100,14 @@ pub fn default_colour_map(
             Box::new(|s| s.to_string()),
             format!("{}", Fg(Reset)),
         ),
+        BgColored(c) => (
+            (format!(
+                "{}",
+                Bg(AnsiValue(colvert::ansi256_from_rgb((c.r, c.g, c.b))))
+            )),
+            Box::new(|s| s.to_string()),
+            format!("{}", Bg(Reset)),
+        ),
         Bell => todo!(),
         NoBreakBegin => (String::new(), Box::new(|s| s.to_string()), String::new()),
         NoBreakEnd => (String::new(), Box::new(|s| s.to_string()), String::new()),
@@ -108,7 +126,7 @@ where
         if _use_colour {
             let result = html2text::custom_render(input, width, default_colour_map).unwrap();
             eprintln!("{:#?}",result);
-            let result = html2text::try_build_block(&result);
+            let result = html2text::try_build_block(&result).unwrap();
             eprintln!("{:#?}",result);
             
             // return process_page(
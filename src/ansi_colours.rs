@@ -6,11 +6,12 @@
 
 use uuid::Uuid;
 
+use crate::render::text_renderer::{TaggedLine, TaggedString};
 use crate::{parse, RichAnnotation, RichDecorator, RenderTree};
 use std::{io, vec};
 
 #[allow(missing_docs)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Control {
     Default,
     RedactedBegin(String, uuid::Uuid),
@@ -25,6 +26,34 @@ pub enum Control {
     StrRedacted(String,uuid::Uuid),
     Audio(String)
 }
+/// 控制序列不合法时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiError {
+    /// `NoBreakEnd` without a `NoBreakBegin` before it.
+    UnmatchedNoBreak,
+    /// `NoBreakBegin` inside a no-break section.
+    NestedSection,
+    /// The end of a redacted region which was never started.
+    RedactionMismatch(Uuid),
+    /// A control which can't appear here, e.g. `RedactedBegin` passed to
+    /// `try_build_block`, or a marker which came with text (or an audio
+    /// marker without a source) in the rendered lines.
+    UnexpectedControl(Control),
+}
+
+impl std::fmt::Display for AnsiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            AnsiError::UnmatchedNoBreak => write!(f, "no-break section ended without starting"),
+            AnsiError::NestedSection => write!(f, "no-break sections can't be nested"),
+            AnsiError::RedactionMismatch(ref id) => write!(f, "redacted region {} ended without starting", id),
+            AnsiError::UnexpectedControl(ref c) => write!(f, "unexpected control {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for AnsiError {}
+
 /// 仅解析,与高度宽度无关
 /// 密码区段的UUid在此过程生成，为了不必重新输入密码，应将此渲染树存储
 pub fn just_parse<R>(input:R) -> RenderTree
@@ -37,13 +66,30 @@ pub fn just_render<FMap>(
     input: RenderTree,
     width: usize,
     map: FMap,
-) -> Result<Vec<Control>, std::fmt::Error>
+) -> Result<Vec<Control>, AnsiError>
 where
     FMap: Fn(&RichAnnotation) -> (String, Box<dyn Fn(&String) -> String>, String),
 {
-    let lines =input
-        .render(width, RichDecorator::new())
-        .into_lines();
+    let lines = input.render(width, RichDecorator::new()).into_lines();
+    lines_to_controls(lines, &map)
+}
+
+/// 把渲染好的行转换成控制序列，供 `just_render` 和 `custom_render` 共用
+fn lines_to_controls<FMap>(
+    lines: Vec<TaggedLine<Vec<RichAnnotation>>>,
+    map: &FMap,
+) -> Result<Vec<Control>, AnsiError>
+where
+    FMap: Fn(&RichAnnotation) -> (String, Box<dyn Fn(&String) -> String>, String),
+{
+    // 标记本身不带文字，带文字说明渲染结果不对
+    let marker = |ts: &TaggedString<Vec<RichAnnotation>>, control: Control| {
+        if ts.s.is_empty() {
+            Ok(())
+        } else {
+            Err(AnsiError::UnexpectedControl(control))
+        }
+    };
     let mut cmds: Vec<Control> = vec![];
     html_trace!("循环开始: lines:{:#?}", lines);
     let mut redacted_stack:Vec<Uuid> = vec![];
@@ -58,40 +104,42 @@ where
             for ann in &ts.tag {
                 match ann {
                     RichAnnotation::NoBreakBegin => {
-                        assert!(&ts.s.is_empty());
+                        marker(ts, Control::NoBreakBegin)?;
                         is_marker = true;
                         cmds.push(Control::NoBreakBegin);
                     }
                     RichAnnotation::RedactedBegin(psk, id) => {
-                        assert!(&ts.s.is_empty());
+                        marker(ts, Control::RedactedBegin(psk.to_string(), *id))?;
                         is_marker = true;
                         redacted_stack.push(*id);
-                        // cmds.push(Control::RedactedBegin(psk.to_string(), *id));
                     }
-                    RichAnnotation::Image(src, w, h) => {
-                        if w * h >= 1 {
-                            // assert!(&ts.s.is_empty());
-                            is_marker = true;
-                            cmds.push(Control::Image(src.to_string(), *w, *h))
-                        } else {
-                        }
+                    // 宽或高为零的图片不占位置，当作普通文字
+                    RichAnnotation::Image(src, w, h) if *w != 0 && *h != 0 => {
+                        is_marker = true;
+                        cmds.push(Control::Image(src.to_string(), *w, *h))
                     },
                     RichAnnotation::RedactedEnd(_, id) => {
-                        assert!(&ts.s.is_empty());
+                        marker(ts, Control::RedactedEnd(*id))?;
                         is_marker = true;
-                        // cmds.push(Control::RedactedEnd(*id));
-                        assert!(redacted_stack.last().unwrap()==id,"密码区段不得嵌套");
-                        redacted_stack.pop();
+                        // 密码区段可以嵌套，按id出栈
+                        match redacted_stack.iter().rposition(|x| x == id) {
+                            Some(pos) => {
+                                redacted_stack.remove(pos);
+                            }
+                            None => return Err(AnsiError::RedactionMismatch(*id)),
+                        }
                     },
                     RichAnnotation::NoBreakEnd => {
-                        assert!(&ts.s.is_empty());
+                        marker(ts, Control::NoBreakEnd)?;
                         is_marker = true;
                         cmds.push(Control::NoBreakEnd)},
                     RichAnnotation::Custom(typ, value) => {
                         if typ == "audio" {
-                            assert!(!value.is_empty());
+                            let src = value
+                                .first()
+                                .ok_or_else(|| AnsiError::UnexpectedControl(Control::Audio(String::new())))?;
                             is_marker = true;
-                            cmds.push(Control::Audio(value[0].clone()))
+                            cmds.push(Control::Audio(src.clone()))
                         } else {
                             html_trace!("遇到不认识的Custom 注解");
                         }
@@ -127,114 +175,116 @@ where
         if !is_marker {
             cmds.push(Control::LF);
         }
-        // html_trace!("YLY: 单元高度:{},单元内容：{:?}",&unit.lines().count(),&unit);
     }
 
     html_trace!("segments:{:?}", cmds);
     Ok(cmds)
 }
 
-/// 重要
-pub fn custom_render<R, FMap>(
-    input: R,
-    width: usize,
-    map: FMap,
-) -> Result<Vec<Control>, std::fmt::Error>
+/// Reads HTML from `input`, and returns text wrapped to `width` columns,
+/// with each span of text between the prefix and suffix (such as terminal
+/// escape codes) which `colour_map` returns for its annotations.
+///
+/// ```rust
+/// use html2text::render::text_renderer::RichAnnotation;
+///
+/// let html = br#"<p>See <a href="http://example.com/">the example</a>.</p>"#;
+/// let text = html2text::from_read_coloured(&html[..], 80, |ann| match ann {
+///     RichAnnotation::Link(_) => ("[".to_string(), "]".to_string()),
+///     _ => (String::new(), String::new()),
+/// });
+/// assert_eq!(text, "See [the example].\n");
+/// ```
+pub fn from_read_coloured<R, FMap>(input: R, width: usize, colour_map: FMap) -> String
 where
     R: io::Read,
-    FMap: Fn(&RichAnnotation) -> (String, Box<dyn Fn(&String) -> String>, String),
+    FMap: Fn(&RichAnnotation) -> (String, String),
 {
-    let lines = parse(input)
-        .render(width, RichDecorator::new())
-        .into_lines();
-    let mut cmds: Vec<Control> = vec![];
-    html_trace!("循环开始: lines:{:#?}", lines);
-    let mut redacted_stack:Vec<Uuid> = vec![];
-    for line in lines {
-        let mut is_marker = false;
+    let mut result = String::new();
+    for line in crate::from_read_rich(input, width) {
         for ts in line.tagged_strings() {
             let mut start = String::new();
             let mut finish = String::new();
-            let mut content = String::new();
-            let mut mutated = false;
-            is_marker = false;
+            // The outer annotation comes first, so its suffix goes last.
             for ann in &ts.tag {
-                match ann {
-                    RichAnnotation::NoBreakBegin => {
-                        assert!(&ts.s.is_empty());
-                        is_marker = true;
-                        cmds.push(Control::NoBreakBegin);
-                    }
-                    RichAnnotation::RedactedBegin(psk, id) => {
-                        assert!(&ts.s.is_empty());
-                        is_marker = true;
-                        redacted_stack.push(*id);
-                        // cmds.push(Control::RedactedBegin(psk.to_string(), *id));
-                    }
-                    RichAnnotation::Image(src, w, h) => {
-                        if w * h >= 1 {
-                            // assert!(&ts.s.is_empty());
-                            is_marker = true;
-                            cmds.push(Control::Image(src.to_string(), *w, *h))
-                        } else {
-                        }
-                    },
-                    RichAnnotation::RedactedEnd(_, id) => {
-                        assert!(&ts.s.is_empty());
-                        is_marker = true;
-                        // cmds.push(Control::RedactedEnd(*id));
-                        assert!(redacted_stack.last().unwrap()==id,"密码区段不得嵌套");
-                        redacted_stack.pop();
-                    },
-                    RichAnnotation::NoBreakEnd => {
-                        assert!(&ts.s.is_empty());
-                        is_marker = true;
-                        cmds.push(Control::NoBreakEnd)},
-                    RichAnnotation::Custom(typ, value) => {
-                        if typ == "audio" {
-                            assert!(!value.is_empty());
-                            is_marker = true;
-                            cmds.push(Control::Audio(value[0].clone()))
-                        } else {
-                            html_trace!("遇到不认识的Custom 注解");
-                        }
-                    }
-                    _ => (),
-                }
-            }
-            if is_marker {
-                break;
+                let (prefix, suffix) = colour_map(ann);
+                start.push_str(&prefix);
+                finish.insert_str(0, &suffix);
             }
-
-            for ann in &ts.tag {
-                mutated = true;
-                let (s, mutator, f) = map(ann);
-                start.push_str(&s);
-                finish.push_str(&f);
-                html_trace!("变化前:{:?}", &ts.s);
-                html_trace!("变化后:{:?}", mutator(&ts.s));
-                content.push_str(&mutator(&ts.s));
-            }
-            let mut s = String::new();
-            if mutated {
-                s += format!("{}{}{}", start, content, finish).as_str();
-            } else {
-                s += format!("{}{}{}", start, ts.s, finish).as_str();
-            }
-            if let Some(id) = redacted_stack.last() {
-                cmds.push(Control::StrRedacted(s, *id))
-            } else {
-                cmds.push(Control::Str(s))
-            }
-        }
-        if !is_marker {
-            cmds.push(Control::LF);
+            result.push_str(&start);
+            result.push_str(&ts.s);
+            result.push_str(&finish);
         }
-        // html_trace!("YLY: 单元高度:{},单元内容：{:?}",&unit.lines().count(),&unit);
+        result.push('\n');
     }
+    result
+}
 
-    html_trace!("segments:{:?}", cmds);
-    Ok(cmds)
+/// 重要
+///
+/// 密码区段可以嵌套，`StrRedacted` 带最内层区段的id：
+///
+/// ```rust
+/// use html2text::Control;
+///
+/// let html = br#"<mask password="p"><p>x</p><mask password="q"><p>y</p></mask><p>z</p></mask>"#;
+/// let plain = |_: &_| (String::new(), Box::new(|s: &String| s.clone()) as Box<dyn Fn(&String) -> String>, String::new());
+/// let cmds = html2text::custom_render(&html[..], 80, plain).unwrap();
+/// let redacted: Vec<_> = cmds
+///     .iter()
+///     .filter_map(|c| match c {
+///         Control::StrRedacted(s, id) => Some((s.as_str(), *id)),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(redacted.len(), 3);
+/// assert_eq!(redacted[0].0, "x");
+/// assert_eq!(redacted[1].0, "y");
+/// assert_eq!(redacted[2].0, "z");
+/// assert_eq!(redacted[0].1, redacted[2].1);
+/// assert_ne!(redacted[0].1, redacted[1].1);
+/// ```
+///
+/// 表格标题和表格在同一个盒子里：
+///
+/// ```rust
+/// use html2text::Control;
+///
+/// let html = b"<p>before</p><table><caption>Cap</caption><tr><td>a</td><td>b</td></tr></table><p>after</p>";
+/// let plain = |_: &_| (String::new(), Box::new(|s: &String| s.clone()) as Box<dyn Fn(&String) -> String>, String::new());
+/// let cmds = html2text::custom_render(&html[..], 80, plain).unwrap();
+/// let blocks = html2text::try_build_block(&cmds).unwrap();
+/// let table = blocks
+///     .iter()
+///     .find(|block| block.inner.contains(&Control::Str("Cap".into())))
+///     .unwrap();
+/// assert!(table.inner.contains(&Control::Str("a│b".into())));
+/// ```
+///
+/// 并排的密码区段（例如在表格的两列里）无法转换成控制序列，返回错误：
+///
+/// ```rust
+/// use html2text::AnsiError;
+///
+/// let html = br#"<table><tr><td><mask password="p">a1<br>a2</mask></td>
+///                <td><mask password="q">b1</mask></td></tr></table>"#;
+/// let plain = |_: &_| (String::new(), Box::new(|s: &String| s.clone()) as Box<dyn Fn(&String) -> String>, String::new());
+/// let result = html2text::custom_render(&html[..], 80, plain);
+/// assert!(matches!(result, Err(AnsiError::RedactionMismatch(_))));
+/// let result = html2text::just_render(html2text::just_parse(&html[..]), 80, plain);
+/// assert!(matches!(result, Err(AnsiError::RedactionMismatch(_))));
+/// ```
+pub fn custom_render<R, FMap>(
+    input: R,
+    width: usize,
+    map: FMap,
+) -> Result<Vec<Control>, AnsiError>
+where
+    R: io::Read,
+    FMap: Fn(&RichAnnotation) -> (String, Box<dyn Fn(&String) -> String>, String),
+{
+    let lines = parse(input).render(width, RichDecorator::new()).into_lines();
+    lines_to_controls(lines, &map)
 }
 
 /// 排版用盒子
@@ -251,15 +301,52 @@ impl Default for PageBlock{
     }
 }
 /// 生成盒子供排版用
-pub fn try_build_block(controls:&Vec<Control>)->Vec<PageBlock>{
+///
+/// 宽或高为零的图片不占位置，也不会切断当前盒子：
+///
+/// ```rust
+/// use html2text::{try_build_block, Control};
+///
+/// let controls = vec![
+///     Control::Str("a".into()),
+///     Control::Image("x.png".into(), 0, 0),
+///     Control::Str("b".into()),
+///     Control::LF,
+/// ];
+/// let blocks = try_build_block(&controls).unwrap();
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].height, 1);
+/// assert!(blocks.iter().all(|block| !block.inner.is_empty()));
+///
+/// let huge = vec![Control::Image("x.png".into(), usize::MAX, usize::MAX)];
+/// assert_eq!(try_build_block(&huge).unwrap()[0].height, usize::MAX);
+/// let flat = vec![Control::Image("x.png".into(), usize::MAX, 0)];
+/// assert!(try_build_block(&flat).unwrap().is_empty());
+/// ```
+///
+/// 不合法的控制序列返回错误：
+///
+/// ```rust
+/// use html2text::{try_build_block, AnsiError, Control};
+///
+/// let nested = vec![Control::NoBreakBegin, Control::NoBreakBegin];
+/// assert_eq!(try_build_block(&nested).unwrap_err(), AnsiError::NestedSection);
+/// let unmatched = vec![Control::Str("a".into()), Control::NoBreakEnd];
+/// assert_eq!(try_build_block(&unmatched).unwrap_err(), AnsiError::UnmatchedNoBreak);
+/// assert_eq!(
+///     try_build_block(&vec![Control::Default]).unwrap_err(),
+///     AnsiError::UnexpectedControl(Control::Default)
+/// );
+/// ```
+pub fn try_build_block(controls:&Vec<Control>)->Result<Vec<PageBlock>, AnsiError>{
     let mut blocks = vec![];
     let mut block = PageBlock { inner: vec![], height: 0 };
     let mut no_break :bool =false;
     for c in controls {
         match c {
-            Control::Default => unreachable!(),
-            Control::RedactedBegin(_, _) => unreachable!(),
-            Control::RedactedEnd(_) => unreachable!(),
+            Control::Default
+            | Control::RedactedBegin(_, _)
+            | Control::RedactedEnd(_) => return Err(AnsiError::UnexpectedControl(c.clone())),
             Control::LF => {
                 block.inner.push(Control::LF);
                 block.height += 1;
@@ -271,7 +358,7 @@ pub fn try_build_block(controls:&Vec<Control>)->Vec<PageBlock>{
             },
             Control::NoBreakBegin => {
                 if no_break {
-                    panic!("Section禁止嵌套");
+                    return Err(AnsiError::NestedSection);
                 };
                 no_break = true;
                 if !block.inner.is_empty() {
@@ -281,19 +368,21 @@ pub fn try_build_block(controls:&Vec<Control>)->Vec<PageBlock>{
             },
             Control::NoBreakEnd => {
                 if !no_break{
-                    panic!("Section不匹配");
+                    return Err(AnsiError::UnmatchedNoBreak);
                 }
                 no_break = false;
                 blocks.push(block);
                 block = PageBlock::default();
             },
+            // 与 custom_render 一致：没有面积的图片直接忽略
+            Control::Image(_, w, h) if *w == 0 || *h == 0 => (),
             Control::Image(src, w, h) => {
                 if !block.inner.is_empty() {
                     blocks.push(block);
                     block = PageBlock::default();
                 }
                 block.inner.push(Control::Image(src.to_string(), *w, *h));
-                block.height += *h;
+                block.height = block.height.saturating_add(*h);
                 blocks.push(block);
                 block=PageBlock::default();
             },
@@ -304,5 +393,5 @@ pub fn try_build_block(controls:&Vec<Control>)->Vec<PageBlock>{
             x => block.inner.push(x.clone()),
         }
     }
-    blocks
+    Ok(blocks)
 }
\ No newline at end of file
@@ -0,0 +1,806 @@
+//! Builder-style configuration for converting HTML to text.
+//!
+//! Start with [`plain`], [`rich`] or [`markdown`], chain any options, then finish with
+//! one of the conversion methods:
+//!
+//! ```rust
+//! let html = b"<table><tr><td>a</td><td>b</td></tr></table>";
+//! let text = html2text::config::plain()
+//!     .width(20)
+//!     .ascii_borders()
+//!     .convert(&html[..]);
+//! assert_eq!(text, "-+-\na|b\n-+-\n");
+//! ```
+
+use std::io;
+
+use crate::render::text_renderer::{
+    BorderStyle, CaptionPosition, HeadingStyle, LinkStyle, MarkdownDecorator, PlainDecorator, RenderOptions,
+    RichDecorator, TaggedLine, TextDecorator,
+};
+use crate::{try_parse_with, Error, RenderTree, RenderedText, Url, DEFAULT_MAX_DEPTH};
+
+/// Options for converting HTML to text, along with the decorator used to
+/// mark up the output.
+#[derive(Clone, Debug)]
+pub struct Config<D: TextDecorator> {
+    decorator: D,
+    width: usize,
+    options: RenderOptions,
+    base_url: Option<Url>,
+    max_depth: usize,
+    keep_hidden: bool,
+}
+
+/// Start a configuration producing plain text, with markup rendered as
+/// text characters and links as footnotes.
+pub fn plain() -> Config<PlainDecorator> {
+    Config::with_decorator(PlainDecorator::new())
+}
+
+/// Start a configuration producing text annotated with `RichAnnotation`s.
+pub fn rich() -> Config<RichDecorator> {
+    Config::with_decorator(RichDecorator::new())
+}
+
+/// Start a configuration producing Markdown.
+///
+/// ```rust
+/// let html = br#"<h2>News</h2><p>Read <em>all</em> about <a href="http://x/">it</a>:</p>
+///                <ul><li><strong>one</strong></li><li><code>two</code></li></ul>"#;
+/// assert_eq!(
+///     html2text::config::markdown().convert(&html[..]),
+///     "## News\n\nRead *all* about [it](http://x/):\n\n- **one**\n- `two`\n"
+/// );
+/// ```
+pub fn markdown() -> Config<MarkdownDecorator> {
+    Config::with_decorator(MarkdownDecorator::new())
+}
+
+impl<D: TextDecorator> Config<D> {
+    /// Start a configuration using a custom decorator.  The width
+    /// defaults to 80 columns.
+    pub fn with_decorator(decorator: D) -> Config<D> {
+        Config {
+            decorator,
+            width: 80,
+            options: RenderOptions::default(),
+            base_url: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            keep_hidden: false,
+        }
+    }
+
+    /// Switch to plain text output, keeping the other options.
+    pub fn plain(self) -> Config<PlainDecorator> {
+        self.decorate(PlainDecorator::new())
+    }
+
+    /// Switch to rich output, keeping the other options.
+    pub fn rich(self) -> Config<RichDecorator> {
+        self.decorate(RichDecorator::new())
+    }
+
+    /// Switch to Markdown output, keeping the other options.
+    pub fn markdown(self) -> Config<MarkdownDecorator> {
+        self.decorate(MarkdownDecorator::new())
+    }
+
+    /// Switch to a different decorator, keeping the other options.
+    pub fn decorate<E: TextDecorator>(self, decorator: E) -> Config<E> {
+        Config {
+            decorator,
+            width: self.width,
+            options: self.options,
+            base_url: self.base_url,
+            max_depth: self.max_depth,
+            keep_hidden: self.keep_hidden,
+        }
+    }
+
+    /// Set the width to wrap the text to.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the characters used for table borders.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::BorderStyle;
+    ///
+    /// let html = b"<table><tr><th>a</th><th>b</th></tr><tr><td>c</td><td>d</td></tr></table>";
+    /// let render = |style| html2text::config::plain().border_style(style).convert(&html[..]);
+    /// assert_eq!(render(BorderStyle::Unicode), "─┬─\na│b\n─┼─\nc│d\n─┴─\n");
+    /// assert_eq!(render(BorderStyle::Ascii), "-+-\na|b\n-+-\nc|d\n-+-\n");
+    /// assert_eq!(render(BorderStyle::None), "a b\nc d\n");
+    /// ```
+    pub fn border_style(mut self, style: BorderStyle) -> Self {
+        self.options.border_style = style;
+        self
+    }
+
+    /// Draw table borders with plain ASCII characters.
+    pub fn ascii_borders(self) -> Self {
+        self.border_style(BorderStyle::Ascii)
+    }
+
+    /// Leave `gap` spaces either side of the separator between table
+    /// columns.
+    ///
+    /// ```rust
+    /// let html = b"<table><tr><td>a</td><td>b</td></tr></table>";
+    /// let text = html2text::config::plain().ascii_borders().column_gap(1).convert(&html[..]);
+    /// assert_eq!(text, "--+--\na | b\n--+--\n");
+    ///
+    /// // Nested tables are given room for their gaps too.
+    /// let html = b"<table><tr><td><table><tr><td>a</td><td>b</td><td>c</td></tr></table></td>\
+    ///              <td>xyz</td></tr></table>";
+    /// let text = html2text::config::plain().column_gap(2).width(30).convert(&html[..]);
+    /// assert_eq!(text, "───┬─────┬─────┬─────\na  │  b  │  c  │  xyz\n───┴─────┴─────┴─────\n");
+    /// ```
+    pub fn column_gap(mut self, gap: usize) -> Self {
+        self.options.column_gap = gap;
+        self
+    }
+
+    /// Put `fence` on a line before and after each preformatted block.
+    /// The opening fence is followed by the language of the code, if the
+    /// HTML gives one with a `language-...` class.
+    ///
+    /// ```rust
+    /// let html = b"<pre class=\"language-rust\">let x = 1;</pre>";
+    /// let fenced = html2text::config::plain().pre_fence("```").convert(&html[..]);
+    /// assert_eq!(fenced, "```rust\nlet x = 1;\n```\n");
+    /// let plain = html2text::config::plain().convert(&html[..]);
+    /// assert_eq!(plain, "let x = 1;\n");
+    /// ```
+    pub fn pre_fence(mut self, fence: &str) -> Self {
+        self.options.pre_fence = Some(fence.to_string());
+        self
+    }
+
+    /// Rejoin words hyphenated across line breaks in the source.
+    ///
+    /// ```rust
+    /// let html = b"<p>an exam-\nple here</p>";
+    /// let text = html2text::config::plain().join_hyphenated().convert(&html[..]);
+    /// assert_eq!(text, "an example here\n");
+    /// ```
+    pub fn join_hyphenated(mut self) -> Self {
+        self.options.join_hyphenated = true;
+        self
+    }
+
+    /// Use `bullets` for unordered lists, cycling by nesting depth.
+    ///
+    /// ```rust
+    /// let html = b"<ul><li>a<ul><li>b<ul><li>c</li></ul></li></ul></li></ul>";
+    /// let text = html2text::config::plain().bullets(&['-', '+', '>']).convert(&html[..]);
+    /// assert_eq!(text, "- a\n  \n  + b\n    \n    > c\n");
+    /// ```
+    pub fn bullets(mut self, bullets: &[char]) -> Self {
+        self.options.bullets = bullets.to_vec();
+        self
+    }
+
+    /// Start each line of a blockquote with `prefix` instead of `"> "`.
+    ///
+    /// ```rust
+    /// let html = b"<blockquote>outer<blockquote>inner</blockquote></blockquote>";
+    /// let text = html2text::config::plain().quote_prefix("| ").convert(&html[..]);
+    /// assert_eq!(text, "| outer\n| \n| | inner\n");
+    /// ```
+    pub fn quote_prefix(mut self, prefix: &str) -> Self {
+        self.options.quote_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Set where figure captions go relative to the figure.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::CaptionPosition;
+    /// let html = b"<figure><figcaption>Cap</figcaption><p>body</p></figure>";
+    /// let above = html2text::config::plain()
+    ///     .figure_caption(CaptionPosition::Above)
+    ///     .convert(&html[..]);
+    /// assert_eq!(above, "Cap\n\nbody\n");
+    /// let below = html2text::config::plain()
+    ///     .figure_caption(CaptionPosition::Below)
+    ///     .convert(&html[..]);
+    /// assert_eq!(below, "body\n\nCap\n");
+    /// ```
+    pub fn figure_caption(mut self, position: CaptionPosition) -> Self {
+        self.options.figure_caption = position;
+        self
+    }
+
+    /// Set how headings are marked.
+    pub fn heading_style(mut self, style: HeadingStyle) -> Self {
+        self.options.heading_style = style;
+        self
+    }
+
+    /// Approximate CSS vertical margins with blank lines.
+    ///
+    /// ```rust
+    /// let html = b"<p>a</p><p style=\"margin-top: 2em\">b</p>";
+    /// let text = html2text::config::plain().css_margins().convert(&html[..]);
+    /// assert_eq!(text, "a\n\n\nb\n");
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a\n\nb\n");
+    ///
+    /// // Later declarations override earlier ones, adjoining margins
+    /// // collapse into the larger, and huge margins are limited.
+    /// let convert = |html: &str| html2text::config::plain().css_margins().convert(html.as_bytes());
+    /// assert_eq!(convert(r#"<p>a</p><p style="margin: 1em; margin-top: 3em">b</p>"#), "a\n\n\n\nb\n\n");
+    /// assert_eq!(
+    ///     convert(r#"<p>a</p><div style="margin-top: 2em"><p style="margin-top: 1em">b</p></div>"#),
+    ///     "a\n\n\nb\n"
+    /// );
+    /// assert_eq!(convert(r#"<p>a</p><p style="margin-top: 99999em">b</p>"#), format!("a{}b\n", "\n".repeat(11)));
+    /// ```
+    pub fn css_margins(mut self) -> Self {
+        self.options.css_margins = true;
+        self
+    }
+
+    /// Stop rendering after `max_nodes` document nodes.  The nodes after
+    /// that aren't visited at all, even to estimate their size, so this
+    /// also limits the work done on a huge document.
+    ///
+    /// ```rust
+    /// let html: String = (0..10000).map(|i| format!("<p>para {}</p>", i)).collect();
+    /// let text = html2text::config::plain().max_nodes(6).convert(html.as_bytes());
+    /// assert_eq!(text, "para 0\n");
+    ///
+    /// // A table is left out if there isn't room for its first row.
+    /// let html = b"<p>x</p><table><tr><td>a</td><td>b</td></tr></table>";
+    /// let convert = |max_nodes| html2text::config::plain().max_nodes(max_nodes).convert(&html[..]);
+    /// assert_eq!(convert(6), "x\n");
+    /// assert_eq!(convert(9), "x\n\n─┬─\na│ \n─┴─\n");
+    /// assert_eq!(convert(100), "x\n\n─┬─\na│b\n─┴─\n");
+    /// ```
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.options.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Keep only the first `max_lines` lines of output, e.g. for a
+    /// preview, followed by an ellipsis line (see
+    /// [`ellipsis`](Config::ellipsis)) if anything was left out.
+    ///
+    /// ```rust
+    /// let convert = |html: &str| html2text::config::plain().max_lines(2).convert(html.as_bytes());
+    /// assert_eq!(convert("<p>one</p>"), "one\n");
+    /// assert_eq!(convert("<p>one<br>two</p>"), "one\ntwo\n");
+    /// assert_eq!(convert("<p>one<br>two<br>three</p>"), "one\ntwo\n…\n");
+    /// ```
+    ///
+    /// A table is cut between rows where possible, and left out rather
+    /// than cut before its first row.
+    ///
+    /// ```rust
+    /// let html = "<table><tr><td>a</td></tr><tr><td>b<br>c</td></tr></table>";
+    /// let text = html2text::config::plain().max_lines(4).convert(html.as_bytes());
+    /// assert_eq!(text, "───\na  \n───\n…\n");
+    ///
+    /// let html = "<p>intro</p><table><tr><td>a</td></tr><tr><td>b</td></tr></table>";
+    /// let convert = |n| html2text::config::plain().max_lines(n).convert(html.as_bytes());
+    /// assert_eq!(convert(1), "intro\n…\n");
+    /// assert_eq!(convert(3), "intro\n…\n");
+    /// assert_eq!(convert(4), "intro\n\n─\na\n…\n");
+    /// assert_eq!(convert(7), "intro\n\n─\na\n─\nb\n─\n");
+    /// assert_eq!(convert(8), "intro\n\n─\na\n─\nb\n─\n");
+    /// ```
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.options.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Set the line added when output is cut short by
+    /// [`max_lines`](Config::max_lines), instead of `"…"`.
+    ///
+    /// ```rust
+    /// let html = b"<p>one</p><p>two</p>";
+    /// let text = html2text::config::plain().max_lines(1).ellipsis("[more]").convert(&html[..]);
+    /// assert_eq!(text, "one\n[more]\n");
+    /// ```
+    pub fn ellipsis(mut self, ellipsis: &str) -> Self {
+        self.options.ellipsis = Some(ellipsis.to_string());
+        self
+    }
+
+    /// Show control characters from the document, such as ESC or BEL,
+    /// as `�` instead of dropping them.  Tabs and newlines are kept as
+    /// usual.  Either way, untrusted HTML can't send terminal escape
+    /// sequences through to the output.
+    ///
+    /// ```rust
+    /// let html = "<p>a\u{1b}[31mb\u{7}c <a href=\"x\u{1b}y\">link</a></p>";
+    /// let text = html2text::from_read(html.as_bytes(), 80);
+    /// assert_eq!(text, "a[31mbc [link][1]\n\n[1]: xy\n");
+    /// let text = html2text::config::plain().replace_control_chars().convert(html.as_bytes());
+    /// assert_eq!(text, "a\u{fffd}[31mb\u{fffd}c [link][1]\n\n[1]: x\u{fffd}y\n");
+    /// ```
+    pub fn replace_control_chars(mut self) -> Self {
+        self.options.replace_control_chars = true;
+        self
+    }
+
+    /// Render `<br>` as `separator` instead of a new line.
+    ///
+    /// ```rust
+    /// let html = b"<p>a<br>b<br>c</p>";
+    /// let text = html2text::config::plain().line_break(" / ").convert(&html[..]);
+    /// assert_eq!(text, "a / b / c\n");
+    /// ```
+    pub fn line_break(mut self, separator: &str) -> Self {
+        self.options.line_break = Some(separator.to_string());
+        self
+    }
+
+    /// Show non-breaking spaces as `marker` instead of as spaces.  Either
+    /// way, lines are never broken at them.
+    ///
+    /// ```rust
+    /// let html = b"<p>It is 10&nbsp;km away</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 8), "It is\n10 km\naway\n");
+    /// let text = html2text::config::plain().width(8).nbsp_marker('_').convert(&html[..]);
+    /// assert_eq!(text, "It is\n10_km\naway\n");
+    ///
+    /// let html = b"<p>a&nbsp;&nbsp;b c</p>";
+    /// let text = html2text::config::plain().nbsp_marker('·').convert(&html[..]);
+    /// assert_eq!(text, "a··b c\n");
+    /// ```
+    pub fn nbsp_marker(mut self, marker: char) -> Self {
+        self.options.nbsp_marker = Some(marker);
+        self
+    }
+
+    /// Set how link targets are shown.
+    pub fn link_style(mut self, style: LinkStyle) -> Self {
+        self.options.link_style = style;
+        self
+    }
+
+    /// Render links as their text alone, as if they weren't links.
+    /// Unlike [`LinkStyle::None`], even the link annotation is left out
+    /// of rich output.
+    ///
+    /// ```rust
+    /// let html = br#"<p>See <a href="u">text</a>.</p>"#;
+    /// assert_eq!(html2text::config::plain().ignore_links().convert(&html[..]), "See text.\n");
+    /// let lines = html2text::config::rich().ignore_links().convert_lines(&html[..]);
+    /// assert!(lines[0].tagged_strings().all(|ts| ts.tag.is_empty()));
+    /// ```
+    pub fn ignore_links(mut self) -> Self {
+        self.options.ignore_links = true;
+        self
+    }
+
+    /// Keep runs of spaces and newlines in text rather than collapsing
+    /// them into single spaces, as with CSS `white-space: pre-wrap`.
+    /// Text is still wrapped between words, and whitespace which only
+    /// indents the markup between blocks is ignored.
+    ///
+    /// ```rust
+    /// let html = b"<div>one  two\nthree</div>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "one two three\n");
+    /// let text = html2text::config::plain().preserve_whitespace().convert(&html[..]);
+    /// assert_eq!(text, "one  two\nthree\n");
+    ///
+    /// let html = b"<div>\n  <p>hello  wonderful world</p>\n  <p>a\tb</p>\n</div>";
+    /// let text = html2text::config::plain().preserve_whitespace().width(10).convert(&html[..]);
+    /// assert_eq!(text, "hello\nwonderful\nworld\n\na       b\n");
+    /// ```
+    pub fn preserve_whitespace(mut self) -> Self {
+        self.options.preserve_whitespace = true;
+        self
+    }
+
+    /// Remove trailing whitespace from each line of the output.  This
+    /// includes the padding which keeps table cells aligned.
+    ///
+    /// ```rust
+    /// let html = b"<p>one two three</p><table><tr><td>a</td><td>bb</td></tr><tr><td>ccc</td><td>d</td></tr></table>";
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 9),
+    ///     "one two\nthree\n\n───┬──\na  │bb\n───┼──\nccc│d \n───┴──\n"
+    /// );
+    /// let config = html2text::config::plain().width(9).trim_trailing_whitespace();
+    /// assert_eq!(
+    ///     config.convert(&html[..]),
+    ///     "one two\nthree\n\n───┬──\na  │bb\n───┼──\nccc│d\n───┴──\n"
+    /// );
+    /// let lines = html2text::config::rich().width(9).trim_trailing_whitespace().convert_lines(&html[..]);
+    /// assert!(lines.iter().any(|line| line.clone().into_string() == "ccc│d"));
+    /// ```
+    pub fn trim_trailing_whitespace(mut self) -> Self {
+        self.options.trim_trailing_whitespace = true;
+        self
+    }
+
+    /// Leave out the newline at the end of the output, e.g. when it's to
+    /// be embedded in other text.
+    ///
+    /// ```rust
+    /// let html = b"<p>hi</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "hi\n");
+    /// let text = html2text::config::plain().no_trailing_newline().convert(&html[..]);
+    /// assert_eq!(text, "hi");
+    /// ```
+    pub fn no_trailing_newline(mut self) -> Self {
+        self.options.no_trailing_newline = true;
+        self
+    }
+
+    /// Keep every blank line between blocks.  By default a run of them,
+    /// e.g. from empty paragraphs, is collapsed into one.  Blank lines
+    /// inside preformatted text are always kept.
+    ///
+    /// ```rust
+    /// let html = b"<div><p>a</p><p></p><div><p></p></div><p>b</p></div><pre>c\n\n\nd</pre>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a\n\nb\n\nc\n\n\nd\n");
+    /// let text = html2text::config::plain().keep_blank_lines().convert(&html[..]);
+    /// assert_eq!(text, "a\n\n\n\nb\n\nc\n\n\nd\n");
+    /// ```
+    pub fn keep_blank_lines(mut self) -> Self {
+        self.options.keep_blank_lines = true;
+        self
+    }
+
+    /// Annotate text with the HTML elements (headings, paragraphs, list
+    /// items, table cells and so on) it came from, so a rich consumer can
+    /// tell e.g. a heading from body text.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    /// let html = b"<h1>Title</h1><ul><li>Item</li></ul>";
+    /// let lines = html2text::config::rich().element_annotations().convert_lines(&html[..]);
+    /// let title = lines[0].tagged_strings().find(|ts| ts.s == "Title").unwrap();
+    /// assert!(title.tag.contains(&RichAnnotation::Element("h1".into())));
+    /// let item = lines[2].tagged_strings().find(|ts| ts.s == "Item").unwrap();
+    /// assert!(item.tag.contains(&RichAnnotation::Element("li".into())));
+    /// ```
+    pub fn element_annotations(mut self) -> Self {
+        self.options.element_annotations = true;
+        self
+    }
+
+    /// Number the items of nested ordered lists after the items they're
+    /// in, e.g. `1.1.`, `1.2.`, instead of starting again from `1.`.
+    ///
+    /// ```rust
+    /// let html = b"<ol><li>One<ol><li>Half</li><li>Third</li></ol></li><li>Two</li></ol>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "1. One\n   \n   1. Half\n   2. Third\n2. Two\n");
+    /// let text = html2text::config::plain().hierarchical_ol().convert(&html[..]);
+    /// assert_eq!(text, "1. One\n   \n   1.1. Half\n   1.2. Third\n2. Two\n");
+    ///
+    /// // Table columns are sized to fit the longer numbers.
+    /// let html = b"<table><tr><td><ol><li>a<ol><li>b<ol><li>c d</li></ol></li></ol></li></ol></td>\
+    ///              <td>more text</td></tr></table>";
+    /// let text = html2text::config::plain().hierarchical_ol().width(24).convert(&html[..]);
+    /// assert_eq!(text, "\
+    /// ──────────────────┬─────
+    /// 1. a              │more 
+    ///                   │text 
+    ///    1.1. b         │     
+    ///                   │     
+    ///         1.1.1. c d│     
+    /// ──────────────────┴─────
+    /// ");
+    /// ```
+    pub fn hierarchical_ol(mut self) -> Self {
+        self.options.hierarchical_ol = true;
+        self
+    }
+
+    /// Escape `<`, `>` and `&` in the output so it can be embedded in HTML.
+    ///
+    /// ```rust
+    /// let html = b"<p>a &lt; b &amp;&amp; c</p>";
+    /// let text = html2text::config::plain().escape_html().convert(&html[..]);
+    /// assert_eq!(text, "a &lt; b &amp;&amp; c\n");
+    /// ```
+    ///
+    /// The escaping is done after layout, so the text lines up once the
+    /// HTML is displayed (e.g. inside a `<pre>`) but not in the raw
+    /// output, where each entity is wider than the character it stands
+    /// for:
+    ///
+    /// ```rust
+    /// let html = b"<table><tr><td>a&lt;b</td><td>c</td></tr><tr><td>d</td><td>e</td></tr></table>";
+    /// let text = html2text::config::plain().escape_html().convert(&html[..]);
+    /// assert_eq!(text, "───┬─\na&lt;b│c\n───┼─\nd  │e\n───┴─\n");
+    /// ```
+    pub fn escape_html(mut self) -> Self {
+        self.options.escape_html = true;
+        self
+    }
+
+    /// Don't leave trailing spaces after the quote markers or indentation
+    /// on blank lines, as is usual for quoted email.
+    ///
+    /// ```rust
+    /// let html = b"<blockquote><p>One</p><blockquote>Two<br><br>Three</blockquote></blockquote>";
+    /// let text = html2text::config::plain().trim_blank_prefixes().convert(&html[..]);
+    /// assert_eq!(text, "> One\n>\n> > Two\n> >\n> > Three\n");
+    /// ```
+    pub fn trim_blank_prefixes(mut self) -> Self {
+        self.options.trim_blank_prefixes = true;
+        self
+    }
+
+    /// Set how narrow a table column of text may be squeezed before the
+    /// table's cells are stacked vertically instead (by default 3).
+    ///
+    /// ```rust
+    /// let html = b"<table><tr><td>alpha</td><td>beta</td><td>gamma</td></tr></table>";
+    /// let text = html2text::config::plain().width(8).convert(&html[..]);
+    /// assert_eq!(text, "────────\nalpha\n////////\nbeta\n////////\ngamma\n────────\n");
+    /// let text = html2text::config::plain().width(8).min_width(2).convert(&html[..]);
+    /// assert_eq!(text, "──┬──┬──\nal│be│ga\nph│ta│mm\na │  │a \n──┴──┴──\n");
+    /// ```
+    pub fn min_width(mut self, min_width: usize) -> Self {
+        self.options.min_width = Some(min_width);
+        self
+    }
+
+    /// Number the captions of figures in the order they appear.
+    ///
+    /// ```rust
+    /// let html = b"<figure>A cat<figcaption>Sleeping</figcaption></figure>\
+    ///              <figure>No caption</figure>\
+    ///              <figure><figcaption>Awake</figcaption>A dog</figure>";
+    /// let text = html2text::config::plain().number_figures().convert(&html[..]);
+    /// assert_eq!(
+    ///     text,
+    ///     "A cat\n\nFigure 1: Sleeping\n\nNo caption\nA dog\n\nFigure 2: Awake\n"
+    /// );
+    /// ```
+    pub fn number_figures(mut self) -> Self {
+        self.options.number_figures = true;
+        self
+    }
+
+    /// Collapse the output onto a single line, e.g. for a log message.
+    /// Words are separated by single spaces, and table borders and column
+    /// separators are dropped.
+    ///
+    /// ```rust
+    /// let html = b"<h1>Title</h1><p>One paragraph.</p><p>And   another\none.</p>\
+    ///              <table><tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr></table>\
+    ///              <pre>x\n  y</pre>";
+    /// let text = html2text::config::plain().single_line().convert(&html[..]);
+    /// assert_eq!(text, "# Title One paragraph. And another one. a b c d x y\n");
+    /// ```
+    pub fn single_line(mut self) -> Self {
+        self.options.single_line = true;
+        self
+    }
+
+    /// Show images like links to their source, so that with the default
+    /// [`LinkStyle`] the source is listed with the link footnotes.
+    ///
+    /// ```rust
+    /// let html = br#"<img src="a.png" alt="A"> <img src="b.png">"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "[A] [No Alt Text Provided]\n");
+    /// assert_eq!(
+    ///     html2text::config::plain().image_links().convert(&html[..]),
+    ///     "[A][1] [image][2]\n\n[1]: a.png\n[2]: b.png\n"
+    /// );
+    ///
+    /// // Only images with no size are shown as links.
+    /// let html = br#"<img width=99999999999 height=99999999999 src="a.png">"#;
+    /// assert!(!html2text::config::plain().image_links().convert(&html[..]).contains("[1]"));
+    /// ```
+    pub fn image_links(mut self) -> Self {
+        self.options.image_links = true;
+        self
+    }
+
+    /// Show an image with no alt text as `placeholder`, which may be
+    /// empty, rather than the decorator's text.
+    ///
+    /// ```rust
+    /// let html = br#"<p>a <img src="a.png"> b</p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a [No Alt Text Provided] b\n");
+    /// let config = html2text::config::plain().image_placeholder("[image]");
+    /// assert_eq!(config.convert(&html[..]), "a [image] b\n");
+    ///
+    /// // Alt text which happens to match the decorator's text is kept.
+    /// let html = br#"<p>a <img src="a.png" alt="No Alt Text Provided"> b</p>"#;
+    /// let config = html2text::config::plain().image_placeholder("[image]");
+    /// assert_eq!(config.convert(&html[..]), "a [No Alt Text Provided] b\n");
+    /// ```
+    pub fn image_placeholder(mut self, placeholder: &str) -> Self {
+        self.options.image_placeholder = Some(placeholder.to_string());
+        self
+    }
+
+    /// Show an image with alt text using `format`, in which `{alt}` and
+    /// `{src}` are replaced by the alt text and the image's source.
+    ///
+    /// ```rust
+    /// let html = br#"<p>a <img src="a.png" alt="cat"> b</p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a [cat] b\n");
+    /// let config = html2text::config::plain().image_format("(image: {alt}, {src})");
+    /// assert_eq!(config.convert(&html[..]), "a (image: cat, a.png) b\n");
+    /// ```
+    pub fn image_format(mut self, format: &str) -> Self {
+        self.options.image_format = Some(format.to_string());
+        self
+    }
+
+    /// Let lines run up to `slack` cells past the width, rather than
+    /// wrapping a word which only just doesn't fit.
+    ///
+    /// ```rust
+    /// let html = b"<p>aaa bbb ccccc dd</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 10), "aaa bbb\nccccc dd\n");
+    /// let text = html2text::config::plain().width(10).width_slack(3).convert(&html[..]);
+    /// assert_eq!(text, "aaa bbb ccccc\ndd\n");
+    /// ```
+    pub fn width_slack(mut self, slack: usize) -> Self {
+        self.options.width_slack = slack;
+        self
+    }
+
+    /// Render table footers at the end of their table, even if the
+    /// `<tfoot>` came earlier in the source.
+    ///
+    /// ```rust
+    /// let html = b"<table><tfoot><tr><td>f</td></tr></tfoot>\
+    ///              <tbody><tr><td>b</td></tr></tbody></table>";
+    /// let text = html2text::config::plain().ascii_borders().convert(&html[..]);
+    /// assert_eq!(text, "-\nf\n-\nb\n-\n");
+    /// let text = html2text::config::plain().ascii_borders().tfoot_last().convert(&html[..]);
+    /// assert_eq!(text, "-\nb\n-\nf\n-\n");
+    /// ```
+    pub fn tfoot_last(mut self) -> Self {
+        self.options.tfoot_last = true;
+        self
+    }
+
+    /// Put `delimiter` either side of highlighted (`<mark>`) text.
+    ///
+    /// ```rust
+    /// let html = b"<p>a <mark>key</mark> point</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a key point\n");
+    /// let text = html2text::config::plain().mark_delimiter("==").convert(&html[..]);
+    /// assert_eq!(text, "a ==key== point\n");
+    /// ```
+    pub fn mark_delimiter(mut self, delimiter: &str) -> Self {
+        self.options.mark_delimiter = Some(delimiter.to_string());
+        self
+    }
+
+    /// Expand tabs in preformatted text to multiples of `width` columns,
+    /// rather than the default of 8.
+    ///
+    /// ```rust
+    /// let html = b"<pre>a\tb</pre>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a       b\n");
+    /// let text = html2text::config::plain().tab_width(4).convert(&html[..]);
+    /// assert_eq!(text, "a   b\n");
+    /// ```
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.options.tab_width = Some(width);
+        self
+    }
+
+    /// Annotate the text of links with their `rel` and `target`
+    /// attributes, as `custom` annotations (`RichAnnotation::Custom` with
+    /// the rich decorator).
+    pub fn link_metadata(mut self) -> Self {
+        self.options.link_metadata = true;
+        self
+    }
+
+    /// Number the lines of preformatted blocks, such as code listings.
+    /// Long lines are wrapped after the numbers.
+    ///
+    /// ```rust
+    /// let html = b"<pre><code>fn main() {\n    go();\n}</code></pre>";
+    /// let text = html2text::config::plain().pre_line_numbers().width(14).convert(&html[..]);
+    /// assert_eq!(text, "1 `fn main() {\n2     go();\n3 }`\n");
+    ///
+    /// // Lines which wrap only repeat the gutter if there is room after it.
+    /// let html = format!("<pre>{}</pre>", "x\tb\n".repeat(12));
+    /// let text = html2text::config::plain().pre_line_numbers().width(3).convert(html.as_bytes());
+    /// let expected: String = (1..=12).map(|n| format!("{:>2} \nx  \n   \nb\n", n)).collect();
+    /// assert_eq!(text, expected);
+    /// ```
+    pub fn pre_line_numbers(mut self) -> Self {
+        self.options.pre_line_numbers = true;
+        self
+    }
+
+    /// Resolve relative link targets and image sources against
+    /// `base_url` when converting.  This has no effect on [`render`],
+    /// as the document has already been parsed.
+    ///
+    /// [`render`]: Config::render
+    pub fn base_url(mut self, base_url: Url) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    /// Flatten elements nested more than `max_depth` deep (256 by
+    /// default) when converting, keeping only the text, images and so on
+    /// inside them.  This bounds the work needed for pathologically deep
+    /// documents.  Like [`base_url`](Config::base_url), it has no effect
+    /// on [`render`](Config::render).
+    ///
+    /// ```rust
+    /// let html = b"<div>a<div><em>b</em></div></div>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a\n*b*\n");
+    /// let text = html2text::config::plain().max_depth(3).convert(&html[..]);
+    /// assert_eq!(text, "ab\n");
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Keep the contents of elements hidden with the `hidden` attribute
+    /// or `display: none`, which are normally dropped.  Like
+    /// [`max_depth`](Config::max_depth), it has no effect on
+    /// [`render`](Config::render).
+    ///
+    /// ```rust
+    /// let html = br#"<p>Read<span hidden> more</span></p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "Read\n");
+    /// let text = html2text::config::plain().keep_hidden().convert(&html[..]);
+    /// assert_eq!(text, "Read more\n");
+    /// ```
+    pub fn keep_hidden(mut self) -> Self {
+        self.keep_hidden = true;
+        self
+    }
+
+    /// Render an already parsed document.
+    pub fn render(self, tree: &RenderTree) -> RenderedText<D> {
+        tree.render_with_options(self.width, self.decorator, self.options)
+    }
+
+    fn parse<R: io::Read>(&self, input: R) -> Result<RenderTree, Error> {
+        try_parse_with(input, self.base_url.as_ref(), self.max_depth, self.keep_hidden)
+    }
+
+    /// Read HTML from `input` and return the converted text.
+    pub fn convert<R: io::Read>(self, input: R) -> String {
+        let tree = self.parse(input).unwrap();
+        self.render(&tree).into_string()
+    }
+
+    /// Read HTML from `input` and return the converted text, or an error
+    /// if the input couldn't be read.
+    pub fn try_convert<R: io::Read>(self, input: R) -> Result<String, Error> {
+        let tree = self.parse(input)?;
+        Ok(self.render(&tree).into_string())
+    }
+
+    /// Read HTML from `input` and return the converted lines with their
+    /// annotations.
+    pub fn convert_lines<R: io::Read>(self, input: R) -> Vec<TaggedLine<Vec<D::Annotation>>> {
+        let tree = self.parse(input).unwrap();
+        self.render(&tree).into_lines()
+    }
+
+    /// Like [`convert_lines`](Config::convert_lines), but returns an error
+    /// if the input couldn't be read.
+    pub fn try_convert_lines<R: io::Read>(
+        self,
+        input: R,
+    ) -> Result<Vec<TaggedLine<Vec<D::Annotation>>>, Error> {
+        let tree = self.parse(input)?;
+        Ok(self.render(&tree).into_lines())
+    }
+}
+
+impl Default for Config<PlainDecorator> {
+    fn default() -> Self {
+        plain()
+    }
+}
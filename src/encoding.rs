@@ -0,0 +1,139 @@
+//! Decoding of HTML which isn't in UTF-8.
+//!
+//! The encoding is taken from a byte order mark if there is one, then from
+//! a `<meta charset>` (or `http-equiv`) declaration near the start of the
+//! document, and otherwise falls back to a default given by the caller.
+
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::io;
+
+/// How far into the document to look for a `<meta>` charset declaration.
+const META_SCAN_LIMIT: usize = 1024;
+
+/// Find the encoding declared in a `<meta>` element near the start of
+/// `bytes`, by looking for `charset=` and taking the label after it.
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(META_SCAN_LIMIT)];
+    let lower = head.to_ascii_lowercase();
+    let mut start = 0;
+    while let Some(pos) = find(&lower[start..], b"<meta") {
+        let tag_start = start + pos;
+        let tag_end = find(&lower[tag_start..], b">").map_or(lower.len(), |end| tag_start + end);
+        let tag = &lower[tag_start..tag_end];
+        if let Some(pos) = find(tag, b"charset") {
+            let value = &tag[pos + b"charset".len()..];
+            let value = &value[value.iter().take_while(|b| b.is_ascii_whitespace()).count()..];
+            if let Some(rest) = value.strip_prefix(b"=") {
+                let label: Vec<u8> = rest
+                    .iter()
+                    .copied()
+                    .skip_while(|b| b.is_ascii_whitespace() || *b == b'"' || *b == b'\'')
+                    .take_while(|b| !(b.is_ascii_whitespace() || b"\"';/>".contains(b)))
+                    .collect();
+                if let Some(encoding) = Encoding::for_label(&label) {
+                    return Some(encoding);
+                }
+            }
+        }
+        start = tag_end;
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Decode the HTML document in `bytes` to a string, using the encoding
+/// from its byte order mark or `<meta>` charset, or `default` if it
+/// doesn't declare one.
+///
+/// ```rust
+/// let html = b"<meta charset=\"windows-1252\"><p>\x93quoted\x94</p>";
+/// assert_eq!(
+///     html2text::decode_html(&html[..], encoding_rs::UTF_8),
+///     "<meta charset=\"windows-1252\"><p>\u{201c}quoted\u{201d}</p>"
+/// );
+/// ```
+pub fn decode_html<'a>(bytes: &'a [u8], default: &'static Encoding) -> Cow<'a, str> {
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => encoding,
+        None => meta_charset(bytes).unwrap_or(default),
+    };
+    encoding.decode_with_bom_removal(bytes).0
+}
+
+/// Reads HTML from `input` in whichever encoding it declares (or in
+/// `default` if it doesn't), and returns a `String` with text wrapped to
+/// `width` columns.
+///
+/// ```rust
+/// // Windows-1252 curly quotes, with no declared encoding.
+/// let html = b"<p>\x93Hello\x94</p>";
+/// assert_eq!(
+///     html2text::from_read_with_encoding(&html[..], 80, encoding_rs::WINDOWS_1252),
+///     "\u{201c}Hello\u{201d}\n"
+/// );
+///
+/// // Shift-JIS, declared in a <meta> element.
+/// let mut html = br#"<meta http-equiv="Content-Type" content="text/html; charset=Shift_JIS">"#.to_vec();
+/// html.extend_from_slice(b"<p>\x93\xfa\x96\x7b\x8c\xea</p>");
+/// assert_eq!(
+///     html2text::from_read_with_encoding(&html[..], 80, encoding_rs::UTF_8),
+///     "\u{65e5}\u{672c}\u{8a9e}\n"
+/// );
+///
+/// // A byte order mark wins over the default.
+/// let html = b"\xef\xbb\xbf<p>caf\xc3\xa9</p>";
+/// assert_eq!(
+///     html2text::from_read_with_encoding(&html[..], 80, encoding_rs::WINDOWS_1252),
+///     "caf\u{e9}\n"
+/// );
+/// ```
+pub fn from_read_with_encoding<R>(input: R, width: usize, default: &'static Encoding) -> String
+where
+    R: io::Read,
+{
+    try_from_read_with_encoding(input, width, default).unwrap()
+}
+
+/// Like [`from_read_with_encoding`], but returns an error if the input
+/// couldn't be read instead of panicking.
+///
+/// ```rust
+/// use std::io;
+///
+/// struct FailingReader;
+///
+/// impl io::Read for FailingReader {
+///     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+///         Err(io::Error::new(io::ErrorKind::Other, "broken pipe"))
+///     }
+/// }
+///
+/// let html = b"<p>\x93Hello\x94</p>";
+/// assert_eq!(
+///     html2text::try_from_read_with_encoding(&html[..], 80, encoding_rs::WINDOWS_1252).unwrap(),
+///     "\u{201c}Hello\u{201d}\n"
+/// );
+/// match html2text::try_from_read_with_encoding(FailingReader, 80, encoding_rs::UTF_8) {
+///     Err(html2text::Error::Io(e)) => assert_eq!(e.to_string(), "broken pipe"),
+///     other => panic!("expected an I/O error, got {:?}", other),
+/// }
+/// ```
+pub fn try_from_read_with_encoding<R>(
+    mut input: R,
+    width: usize,
+    default: &'static Encoding,
+) -> Result<String, crate::Error>
+where
+    R: io::Read,
+{
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let html = decode_html(&bytes, default);
+    crate::try_from_read(html.as_bytes(), width)
+}
@@ -58,11 +58,14 @@ extern crate unicode_width;
 #[macro_use]
 mod macros;
 
+pub mod config;
 pub mod render;
 
+pub use url::Url;
+
 use render::text_renderer::{
-    RenderLine, RichAnnotation, RichDecorator, SubRenderer, TaggedLine,
-    TextDecorator, TextRenderer,
+    BorderStyle, CaptionPosition, HeadingStyle, RenderLine, RenderOptions, RichAnnotation, RichDecorator,
+    SubRenderer, TaggedLine, TextDecorator, TextRenderer,
 };
 use render::Renderer;
 
@@ -81,6 +84,8 @@ use std::cmp::{max, min};
 use std::io;
 use std::io::Write;
 use std::iter::{once, repeat};
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
 
 /// A dummy writer which does nothing
 struct Discard {}
@@ -112,6 +117,16 @@ impl Default for SizeEstimate {
 }
 
 impl SizeEstimate {
+    /// The rough overall width of the content, as if on one line.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The narrowest width the content can be wrapped to.
+    pub fn min_width(&self) -> usize {
+        self.min_width
+    }
+
     /// Combine two estimates into one (add size and widest required)
     pub fn add(self, other: SizeEstimate) -> SizeEstimate {
         SizeEstimate {
@@ -131,14 +146,32 @@ impl SizeEstimate {
 
 #[derive(Clone, Debug)]
 /// Render tree table cell
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub struct RenderTableCell {
     colspan: usize,
     content: Vec<RenderNode>,
-    size_estimate: Cell<Option<SizeEstimate>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    size_estimate: Cell<Option<(usize, SizeEstimate)>>,
     col_width: Option<usize>, // Actual width to use
+    header: bool,             // True for a <th>
 }
 
 impl RenderTableCell {
+    /// Create a cell spanning `colspan` columns.
+    pub fn new(colspan: usize, content: Vec<RenderNode>) -> RenderTableCell {
+        RenderTableCell {
+            colspan,
+            content,
+            size_estimate: Cell::new(None),
+            col_width: None,
+            header: false,
+        }
+    }
+
     /// Render this cell to a renderer.
     pub fn render<T: Write, D: TextDecorator>(
         &mut self,
@@ -149,28 +182,60 @@ impl RenderTableCell {
         //render_tree_children_to_string(builder, &mut self.content, err_out)
     }
 
-    /// Calculate or return the estimate size of the cell
-    pub fn get_size_estimate(&self) -> SizeEstimate {
-        if self.size_estimate.get().is_none() {
-            let size = self
-                .content
-                .iter()
-                .map(|node| node.get_size_estimate())
-                .fold(Default::default(), SizeEstimate::add);
-            self.size_estimate.set(Some(size));
+    /// Calculate or return the estimate size of the cell.  This includes
+    /// the width of list markers, so that a numbered list in a narrow
+    /// column keeps its numbers on the same line as the items.
+    ///
+    /// ```rust
+    /// let html = "<table><tr><td><ol start=9><li>ab</li><li>cd</li></ol></td>\
+    ///             <td>xxxxxxxxxxxxxxxxxx yyyyyyyyyyyyyy</td></tr></table>";
+    /// let text = html2text::from_html(html, 20);
+    /// assert!(text.contains("\n9.  ab│"));
+    /// assert!(text.contains("\n10. cd│"));
+    /// ```
+    pub fn get_size_estimate(&self, min_width: usize) -> SizeEstimate {
+        match self.size_estimate.get() {
+            Some((m, size)) if m == min_width => size,
+            _ => {
+                let size = self
+                    .content
+                    .iter()
+                    .map(|node| node.get_size_estimate(min_width))
+                    .fold(Default::default(), SizeEstimate::add);
+                self.size_estimate.set(Some((min_width, size)));
+                size
+            }
         }
-        self.size_estimate.get().unwrap()
     }
 }
 
 #[derive(Clone, Debug)]
 /// Render tree table row
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub struct RenderTableRow {
     cells: Vec<RenderTableCell>,
     col_sizes: Option<Vec<usize>>,
+    header: bool, // True for a row in the <thead>
+    footer: bool, // True for a row in the <tfoot>
+    column_gap: usize, // Spaces either side of each column separator
 }
 
 impl RenderTableRow {
+    /// Create a row with the given cells.
+    pub fn new(cells: Vec<RenderTableCell>) -> RenderTableRow {
+        RenderTableRow {
+            cells,
+            col_sizes: None,
+            header: false,
+            footer: false,
+            column_gap: 0,
+        }
+    }
+
     /// Return a mutable iterator over the cells.
     pub fn cells(&self) -> std::slice::Iter<RenderTableCell> {
         self.cells.iter()
@@ -182,7 +247,7 @@ impl RenderTableRow {
     /// Count the number of cells in the row.
     /// Takes into account colspan.
     pub fn num_cells(&self) -> usize {
-        self.cells.iter().map(|cell| cell.colspan).sum()
+        self.cells.iter().map(|cell| cell.colspan.max(1)).sum()
     }
     /// Return an iterator over (column, &cell)s, which
     /// takes into account colspan.
@@ -200,43 +265,195 @@ impl RenderTableRow {
     /// Return the contained cells as RenderNodes, annotated with their
     /// widths if available.  Skips cells with no width allocated.
     pub fn into_cells(self, vertical: bool) -> Vec<RenderNode> {
-        let mut result = Vec::new();
+        let col_sizes = self.col_sizes.as_deref().unwrap();
+        let widths = self.cell_widths(col_sizes, self.column_gap, vertical);
+        self.cells
+            .into_iter()
+            .zip(widths)
+            .filter_map(|(mut cell, width)| {
+                cell.col_width = Some(width?);
+                Some(RenderNode::new(RenderNodeInfo::TableCell(cell)))
+            })
+            .collect()
+    }
+
+    /// Return the width of each cell with the given column sizes,
+    /// including the borders between the columns it spans, or `None` if
+    /// it has no width allocated.
+    fn cell_widths(&self, col_sizes: &[usize], column_gap: usize, vertical: bool) -> Vec<Option<usize>> {
         let mut colno = 0;
-        let col_sizes = self.col_sizes.unwrap();
-        for mut cell in self.cells {
-            let colspan = cell.colspan;
-            let col_width = if vertical {
-                col_sizes[colno]
-            } else {
-                col_sizes[colno..colno + cell.colspan].iter().sum::<usize>()
-            };
-            // Skip any zero-width columns
-            if col_width > 0 {
-                cell.col_width = Some(col_width + cell.colspan - 1);
-                result.push(RenderNode::new(RenderNodeInfo::TableCell(cell)));
-            }
-            colno += colspan;
-        }
-        result
+        self.cells
+            .iter()
+            .map(|cell| {
+                let spanned = if vertical {
+                    &col_sizes[colno..colno + 1]
+                } else {
+                    &col_sizes[colno..colno + cell.colspan]
+                };
+                colno += cell.colspan;
+                let col_width = spanned.iter().sum::<usize>();
+                // Skip any zero-width columns
+                if col_width > 0 {
+                    // Zero-width columns have no borders to take over either.
+                    let borders = spanned.iter().filter(|&&w| w > 0).count() - 1;
+                    Some(col_width + borders * (1 + 2 * column_gap))
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
 }
 
 #[derive(Clone, Debug)]
 /// A representation of a table render tree with metadata.
+///
+/// Rows with fewer cells than the table has columns are padded with
+/// empty cells, so the borders still line up:
+/// ```rust
+/// let html = b"<table><tr><td>a</td><td>b</td><td>c</td></tr><tr><td>d</td></tr></table>";
+/// assert_eq!(
+///     html2text::from_read(&html[..], 80),
+///     "─┬─┬─\na│b│c\n─┼─┼─\nd│ │ \n─┴─┴─\n"
+/// );
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub struct RenderTable {
     rows: Vec<RenderTableRow>,
     num_columns: usize,
-    size_estimate: Cell<Option<SizeEstimate>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    size_estimate: Cell<Option<(usize, SizeEstimate)>>,
+    borderless: bool,
+    column_weights: Vec<Option<usize>>,
+    caption: Vec<RenderNode>,
 }
 
 impl RenderTable {
-    /// Create a new RenderTable with the given rows
-    pub fn new(rows: Vec<RenderTableRow>) -> RenderTable {
+    /// Create a new RenderTable with the given rows.  A cell with
+    /// `colspan="0"` spans the rest of its row.
+    ///
+    /// A cell spanning every column is a banner across the full width of
+    /// the table, which is sized to fit the other rows.
+    ///
+    /// ```rust
+    /// let html = r#"<table><tr><td colspan="2">A banner across the table</td></tr>
+    ///               <tr><td>alpha</td><td>beta gamma</td></tr></table>"#;
+    /// assert_eq!(
+    ///     html2text::from_html(html, 40),
+    ///     "────────────────\nA banner across \nthe table       \n\
+    ///      ─────┬──────────\nalpha│beta gamma\n─────┴──────────\n"
+    /// );
+    /// ```
+    pub fn new(mut rows: Vec<RenderTableRow>) -> RenderTable {
         let num_columns = rows.iter().map(|r| r.num_cells()).max().unwrap_or(0);
+        for row in &mut rows {
+            let spare = num_columns - row.num_cells();
+            if let Some(cell) = row.cells.iter_mut().find(|cell| cell.colspan == 0) {
+                cell.colspan = spare + 1;
+            }
+            for cell in &mut row.cells {
+                cell.colspan = cell.colspan.max(1);
+            }
+            for _ in row.num_cells()..num_columns {
+                row.cells.push(RenderTableCell::new(1, Vec::new()));
+            }
+        }
         RenderTable {
             rows,
             num_columns,
             size_estimate: Cell::new(None),
+            borderless: false,
+            column_weights: Vec::new(),
+            caption: Vec::new(),
+        }
+    }
+
+    /// Draw this table (and any tables inside it) without borders, as
+    /// for a layout table with `border="0"`.
+    ///
+    /// ```rust
+    /// let html = br#"<table border="0"><tr><td>a</td><td>b</td></tr></table>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 20), "a b\n");
+    /// ```
+    pub fn set_borderless(&mut self, borderless: bool) {
+        self.borderless = borderless;
+    }
+
+    /// Set the relative widths wanted for the columns, e.g. from
+    /// `<col width="...">`.  The space the table's columns would have
+    /// taken is shared between those with a weight in proportion to it,
+    /// though no column is made narrower than its minimum width.
+    ///
+    /// ```rust
+    /// let html = br#"<table><colgroup><col width="75%"><col width="25%"></colgroup>
+    ///                <tr><td>one two three four five six seven</td>
+    ///                <td>one two three four five six seven</td></tr></table>"#;
+    /// let text = html2text::from_read(&html[..], 40);
+    /// let columns: Vec<usize> = text.lines().nth(1).unwrap().split('│').map(str::len).collect();
+    /// assert_eq!(columns, vec![29, 10]);
+    ///
+    /// let html = br#"<table><col width="100"><col width="300">
+    ///                <tr><td>one two three four five six seven</td>
+    ///                <td>one two three four five six seven</td></tr></table>"#;
+    /// let text = html2text::from_read(&html[..], 41);
+    /// let columns: Vec<usize> = text.lines().nth(1).unwrap().split('│').map(str::len).collect();
+    /// assert_eq!(columns, vec![10, 30]);
+    /// ```
+    ///
+    /// Huge widths and spans are clamped, and if the hints mix pixels and
+    /// percentages they can't be compared, so are all ignored:
+    ///
+    /// ```rust
+    /// let html = br#"<table><col width="18446744073709551615"><col span="1000000000000" width="1">
+    ///                <tr><td>a</td><td>b</td></tr></table>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 20), "─┬─\na│b\n─┴─\n");
+    ///
+    /// let html = br#"<table><col width="90%"><col width="10px">
+    ///                <tr><td>one two three four five six seven</td>
+    ///                <td>one two three four five six seven</td></tr></table>"#;
+    /// let text = html2text::from_read(&html[..], 41);
+    /// let columns: Vec<usize> = text.lines().nth(1).unwrap().split('│').map(str::len).collect();
+    /// assert_eq!(columns, vec![20, 20]);
+    /// ```
+    pub fn set_column_weights(&mut self, mut weights: Vec<Option<usize>>) {
+        weights.truncate(self.num_columns);
+        self.column_weights = weights;
+    }
+
+    /// Set the contents of the table's caption.  It's centred over the
+    /// table, and wrapped to the table's width rather than the page's,
+    /// unless it has a word too long for that.  A table with a caption
+    /// is kept together when paginating, which leaves a blank line either
+    /// side of it.
+    ///
+    /// ```rust
+    /// let html = b"<table><caption>A rather long caption describing this small \
+    ///              table in detail</caption><tr><td>a</td><td>b</td></tr>\
+    ///              <tr><td>one two</td><td>three</td></tr></table>";
+    /// let text = html2text::from_read(&html[..], 40);
+    /// assert_eq!(
+    ///     text.trim_matches('\n'),
+    ///     "A rather long\n   caption\n describing\n this small\n  table in\n   detail\n\
+    ///      ───────┬─────\na      │b    \n───────┼─────\none two│three\n───────┴─────"
+    /// );
+    /// ```
+    pub fn set_caption(&mut self, caption: Vec<RenderNode>) {
+        self.caption = caption;
+    }
+
+    /// Return the rows in the order to render them: with `footers_last`,
+    /// the rows from any `<tfoot>` are moved to the end of the table.
+    fn ordered_rows(&self, footers_last: bool) -> Vec<&RenderTableRow> {
+        if footers_last {
+            let (footers, mut rows): (Vec<_>, Vec<_>) = self.rows.iter().partition(|row| row.footer);
+            rows.extend(footers);
+            rows
+        } else {
+            self.rows.iter().collect()
         }
     }
 
@@ -250,24 +467,22 @@ impl RenderTable {
         self.rows.iter_mut()
     }
     /// Consume this and return a Vec<RenderNode> containing the children;
-    /// the children know the column sizes required.
-    pub fn into_rows(self, col_sizes: Vec<usize>, vert: bool) -> Vec<RenderNode> {
+    /// the children know the column sizes required, and the `column_gap`
+    /// to leave either side of the separators.
+    pub fn into_rows(self, col_sizes: Vec<usize>, column_gap: usize, vert: bool) -> Vec<RenderNode> {
         self.rows
             .into_iter()
             .map(|mut tr| {
                 tr.col_sizes = Some(col_sizes.clone());
+                tr.column_gap = column_gap;
                 RenderNode::new(RenderNodeInfo::TableRow(tr, vert))
             })
             .collect()
     }
 
-    fn calc_size_estimate(&self) {
+    fn calc_size_estimate(&self, text_min_width: usize) -> SizeEstimate {
         if self.num_columns == 0 {
-            self.size_estimate.set(Some(SizeEstimate {
-                size: 0,
-                min_width: 0,
-            }));
-            return;
+            return Default::default();
         }
         let mut sizes: Vec<SizeEstimate> = vec![Default::default(); self.num_columns];
 
@@ -275,34 +490,115 @@ impl RenderTable {
         for row in self.rows() {
             let mut colno = 0usize;
             for cell in row.cells() {
-                let cellsize = cell.get_size_estimate();
+                let cellsize = cell.get_size_estimate(text_min_width);
+                // Spread a spanning cell evenly over its columns, rounding
+                // the minimum up so that the columns together still fit it.
+                let min_width = (cellsize.min_width + cell.colspan - 1) / cell.colspan;
                 for colnum in 0..cell.colspan {
                     sizes[colno + colnum].size += cellsize.size / cell.colspan;
-                    sizes[colno + colnum].min_width = max(
-                        sizes[colno + colnum].min_width / cell.colspan,
-                        cellsize.min_width,
-                    );
+                    sizes[colno + colnum].min_width =
+                        max(sizes[colno + colnum].min_width, min_width);
                 }
                 colno += cell.colspan;
             }
         }
-        let size = sizes.iter().map(|s| s.size).sum(); // Include borders?
-        let min_width = sizes.iter().map(|s| s.min_width).sum::<usize>() + self.num_columns - 1;
-        self.size_estimate
-            .set(Some(SizeEstimate { size, min_width }));
+        // Both include the column separators.
+        let separators = self.num_columns - 1;
+        let size = sizes.iter().map(|s| s.size).sum::<usize>() + separators;
+        let min_width = sizes.iter().map(|s| s.min_width).sum::<usize>() + separators;
+        // The caption can wrap to the table's width, but needs room for
+        // its longest word.
+        let caption_width = self
+            .caption
+            .iter()
+            .map(|node| node.get_size_estimate(text_min_width).min_width)
+            .max()
+            .unwrap_or(0);
+        SizeEstimate {
+            size: max(size, caption_width),
+            min_width: max(min_width, caption_width),
+        }
     }
 
-    /// Calculate and store (or return stored value) of estimated size
-    pub fn get_size_estimate(&self) -> SizeEstimate {
-        if self.size_estimate.get().is_none() {
-            self.calc_size_estimate();
+    /// Calculate and store (or return stored value) of estimated size.
+    /// A cell spanning several columns needs no more room than the same
+    /// content split between separate cells.
+    ///
+    /// ```rust
+    /// let spanned = "<table><tr><td><table><tr><td colspan=2><h1>abc</h1></td></tr>\
+    ///                <tr><td>a</td><td>b</td></tr></table></td><td>xyz</td></tr></table>";
+    /// let separate = "<table><tr><td><table><tr><td>abc</td><td>def</td></tr>\
+    ///                 <tr><td>a</td><td>b</td></tr></table></td><td>xyz</td></tr></table>";
+    /// for html in &[spanned, separate] {
+    ///     let text = html2text::from_html(html, 14);
+    ///     assert!(text.lines().nth(1).unwrap().ends_with("│xyz"), "{}", text);
+    /// }
+    /// ```
+    ///
+    /// A spanning cell's size is shared between its columns, and each
+    /// column needs at least its share of the cell's minimum width:
+    ///
+    /// ```rust
+    /// use html2text::{RenderNode, RenderNodeInfo, RenderTable, RenderTableCell, RenderTableRow};
+    /// let cell = |colspan, text: &str| {
+    ///     RenderTableCell::new(colspan, vec![RenderNode::new(RenderNodeInfo::Text(text.into()))])
+    /// };
+    /// let spanned = RenderTable::new(vec![
+    ///     RenderTableRow::new(vec![cell(2, "abcdefgh")]),
+    ///     RenderTableRow::new(vec![cell(1, "a"), cell(1, "b")]),
+    /// ]);
+    /// let estimate = spanned.get_size_estimate(3);
+    /// assert_eq!((estimate.size(), estimate.min_width()), (11, 5));
+    ///
+    /// let separate = RenderTable::new(vec![
+    ///     RenderTableRow::new(vec![cell(1, "abcd"), cell(1, "efgh")]),
+    ///     RenderTableRow::new(vec![cell(1, "a"), cell(1, "b")]),
+    /// ]);
+    /// let estimate = separate.get_size_estimate(3);
+    /// assert_eq!((estimate.size(), estimate.min_width()), (11, 7));
+    /// ```
+    pub fn get_size_estimate(&self, min_width: usize) -> SizeEstimate {
+        match self.size_estimate.get() {
+            Some((m, size)) if m == min_width => size,
+            _ => {
+                let size = self.calc_size_estimate(min_width);
+                self.size_estimate.set(Some((min_width, size)));
+                size
+            }
         }
-        self.size_estimate.get().unwrap()
+    }
+}
+
+/// The horizontal alignment of the lines of a block, from `text-align`,
+/// an `align` attribute or `dir="rtl"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
+pub enum Alignment {
+    /// Lines start at the left edge.
+    Left,
+    /// Lines are centred.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Alignment::Left
     }
 }
 
 /// Color 
 #[derive(Clone,Copy,Eq,PartialEq,Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Color {
     /// Red
     pub r:u8,
@@ -327,43 +623,368 @@ impl Color {
         }
     }
 }
+/// The numbering style of an ordered list (from the `type` attribute).
+///
+/// ```rust
+/// let html = b"<ol type=\"a\"><li>a</li><li>b</li><li>c</li><li>d</li><li>e</li></ol>";
+/// assert_eq!(
+///     html2text::from_read(&html[..], 80),
+///     "a. a\nb. b\nc. c\nd. d\ne. e\n"
+/// );
+/// let html = b"<ol type=\"i\"><li>a</li><li>b</li><li>c</li><li>d</li><li>e</li></ol>";
+/// assert_eq!(
+///     html2text::from_read(&html[..], 80),
+///     "i.   a\nii.  b\niii. c\niv.  d\nv.   e\n"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
+pub enum OrderedListStyle {
+    /// Decimal numbers (`1`, `2`, `3`)
+    Decimal,
+    /// Lowercase letters (`a`, `b`, `c`)
+    LowerAlpha,
+    /// Uppercase letters (`A`, `B`, `C`)
+    UpperAlpha,
+    /// Lowercase Roman numerals (`i`, `ii`, `iii`)
+    LowerRoman,
+    /// Uppercase Roman numerals (`I`, `II`, `III`)
+    UpperRoman,
+}
+
+impl OrderedListStyle {
+    /// Parse the value of an `<ol type="...">` attribute.
+    fn from_type_attr(value: &str) -> Option<OrderedListStyle> {
+        use OrderedListStyle::*;
+        match value {
+            "1" => Some(Decimal),
+            "a" => Some(LowerAlpha),
+            "A" => Some(UpperAlpha),
+            "i" => Some(LowerRoman),
+            "I" => Some(UpperRoman),
+            _ => None,
+        }
+    }
+}
+
+/// Return the marker for the `index`th item of an ordered list, without
+/// any decoration.  Values which can't be represented in the requested
+/// style (such as zero or negative numbers) fall back to decimal.
+fn list_marker(index: i64, style: OrderedListStyle) -> String {
+    use OrderedListStyle::*;
+    match style {
+        LowerAlpha | UpperAlpha if index > 0 => {
+            // Bijective base 26: a..z, aa..az, ...
+            let base = if style == LowerAlpha { b'a' } else { b'A' };
+            let mut n = index;
+            let mut letters = Vec::new();
+            while n > 0 {
+                n -= 1;
+                letters.push((base + (n % 26) as u8) as char);
+                n /= 26;
+            }
+            letters.iter().rev().collect()
+        }
+        LowerRoman | UpperRoman if index > 0 && index < 4000 => {
+            const NUMERALS: [(i64, &str); 13] = [
+                (1000, "m"),
+                (900, "cm"),
+                (500, "d"),
+                (400, "cd"),
+                (100, "c"),
+                (90, "xc"),
+                (50, "l"),
+                (40, "xl"),
+                (10, "x"),
+                (9, "ix"),
+                (5, "v"),
+                (4, "iv"),
+                (1, "i"),
+            ];
+            let mut n = index;
+            let mut result = String::new();
+            for &(value, numeral) in NUMERALS.iter() {
+                while n >= value {
+                    result.push_str(numeral);
+                    n -= value;
+                }
+            }
+            if style == UpperRoman {
+                result.make_ascii_uppercase();
+            }
+            result
+        }
+        _ => index.to_string(),
+    }
+}
+
 /// The node-specific information distilled from the DOM.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub enum RenderNodeInfo {
     /// Some text.
     Text(String),
     /// A group of nodes collected together.
     Container(Vec<RenderNode>),
-    /// A link with contained nodes
-    Link(String, Vec<RenderNode>),
+    /// A link with its target, title, `rel` and `target` attributes
+    /// (as name/value pairs) and contained nodes.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = br#"<a href="http://example.com/" rel="nofollow">x</a>"#;
+    /// let lines = html2text::config::rich().link_metadata().convert_lines(&html[..]);
+    /// let link = lines[0].tagged_strings().find(|ts| ts.s == "x").unwrap();
+    /// let rel = RichAnnotation::Custom("rel".into(), vec!["nofollow".into()]);
+    /// assert!(link.tag.contains(&rel));
+    /// ```
+    Link(String, Option<String>, Vec<(String, String)>, Vec<RenderNode>),
+    /// A superscripted link on its own, as used for citations (target,
+    /// title, link text).  With footnote links the plain decorator shows
+    /// just the footnote number.
+    ///
+    /// ```rust
+    /// let html = br##"<p>Water is wet<sup><a href="#cite">[1]</a></sup>.</p>"##;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "Water is wet[1].\n\n[1]: #cite\n");
+    /// ```
+    Citation(String, Option<String>, String),
     /// An emphasised region
     Em(Vec<RenderNode>),
     /// A strong region
     Strong(Vec<RenderNode>),
     /// A struck out region
     Strikeout(Vec<RenderNode>),
-    /// A colored region
+    /// An underlined region
+    Underline(Vec<RenderNode>),
+    /// De-emphasised (`<small>`) text, unchanged in plain output.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = b"<p>Price <small>excl. VAT</small></p>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "Price excl. VAT\n");
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let small: Vec<_> = lines[0]
+    ///     .tagged_strings()
+    ///     .filter(|ts| ts.tag.contains(&RichAnnotation::Small))
+    ///     .map(|ts| ts.s.as_str())
+    ///     .collect();
+    /// assert_eq!(small.concat(), "excl. VAT");
+    /// ```
+    Small(Vec<RenderNode>),
+    /// A highlighted region
+    Mark(Vec<RenderNode>),
+    /// An inline quotation, with the URL from its `cite` attribute.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = br#"<q cite="http://example.com/">Hi</q>"#;
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let quoted = lines[0].tagged_strings().find(|ts| ts.s.contains("Hi")).unwrap();
+    /// let cite = RichAnnotation::Custom("cite".into(), vec!["http://example.com/".into()]);
+    /// assert!(quoted.tag.contains(&cite));
+    /// ```
+    Quote(Option<String>, Vec<RenderNode>),
+    /// A region with a background colour, from `<mark>` (yellow) or a CSS
+    /// `background-color` style.  Plain text output ignores the colour.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    /// use html2text::Color;
+    ///
+    /// let html = b"<p>a <mark>highlight</mark></p>";
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let marked = lines[0].tagged_strings().find(|ts| ts.s == "highlight").unwrap();
+    /// assert!(marked.tag.contains(&RichAnnotation::BgColored(Color::new(255, 255, 0))));
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a highlight\n");
+    /// ```
+    BgColored(Vec<RenderNode>, Color),
+    /// A colored region, from `<font color>` or a CSS `color` style.  Plain
+    /// text output ignores the colour.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    /// use html2text::Color;
+    ///
+    /// let html = br##"<p>a <span style="color: #ff0000">red</span> <font color="navy">b</font></p>"##;
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let tags: Vec<_> = lines[0].tagged_strings().map(|ts| (ts.s.as_str(), &ts.tag)).collect();
+    /// assert_eq!(tags[1], ("red", &vec![RichAnnotation::Colored(Color::new(255, 0, 0))]));
+    /// assert_eq!(tags[3], ("b", &vec![RichAnnotation::Colored(Color::new(0, 0, 0x80))]));
+    /// assert_eq!(html2text::from_read(&html[..], 80), "a red b\n");
+    /// ```
     Colored(Vec<RenderNode>,Color),
-    /// A password-protected region
-    Redacted(Vec<RenderNode>,String,uuid::Uuid),
+    /// A password-protected region.  The password is left out when the
+    /// tree is serialised.
+    Redacted(
+        Vec<RenderNode>,
+        #[cfg_attr(feature = "serde", serde(skip))] String,
+        #[cfg_attr(feature = "serde", serde(skip))] uuid::Uuid,
+    ),
     /// A code region
     Code(Vec<RenderNode>),
-    /// An image (src, title)
-    Img(String, String,usize,usize),
-    /// A block element with children
-    Block(Vec<RenderNode>),
+    /// An image (src, alt text if any, width, height)
+    ///
+    /// A `<picture>` is shown as the `<img>` inside it, ignoring its
+    /// `<source>`s.
+    ///
+    /// ```rust
+    /// let html = br#"<p><picture><source srcset="x.webp" type="image/webp">
+    ///                <source srcset="x.avif" type="image/avif">
+    ///                <img src="x.png" alt="x"></picture></p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "[x]\n");
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let image = html2text::render::text_renderer::RichAnnotation::Image("x.png".into(), 0, 0);
+    /// assert_eq!(lines[0].tagged_strings().next().unwrap().tag, vec![image]);
+    /// ```
+    Img(String, Option<String>,usize,usize),
+    /// A block element with children, and the alignment of its lines.
+    ///
+    /// ```rust
+    /// let html = br#"<p style="text-align: center">centred</p><p align="right">right</p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 11), "  centred\n\n      right\n");
+    /// let html = br#"<p align="center">one two three</p><p style="text-align:right">one two three</p>"#;
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 9),
+    ///     " one two\n  three\n\n  one two\n    three\n"
+    /// );
+    /// // Right-to-left text is right-aligned, but not reordered.
+    /// let html = "<p dir=\"rtl\">שלום עולם</p><div dir=\"rtl\">مرحبا</div>";
+    /// assert_eq!(
+    ///     html2text::from_read(html.as_bytes(), 12),
+    ///     "   שלום עולם\n\n       مرحبا\n"
+    /// );
+    /// ```
+    Block(Alignment, Vec<RenderNode>),
+    /// A list item starting with a checkbox (checked, children).  The
+    /// checkbox becomes the item's bullet; checkboxes and radio buttons
+    /// anywhere else are rendered in place.
+    ///
+    /// ```rust
+    /// let html = "<ul><li><input type=checkbox checked> Done</li></ul>\
+    ///             <p><input type=checkbox checked> Yes <input type=checkbox> No</p>";
+    /// assert_eq!(html2text::from_html(html, 80), "[x] Done\n\n[x] Yes [ ] No\n");
+    ///
+    /// let html = "<ul><li><input type=checkbox checked> done</li>\
+    ///             <li><input type=checkbox> todo</li></ul>";
+    /// assert_eq!(html2text::from_html(html, 80), "[x] done\n[ ] todo\n");
+    /// ```
+    TaskItem(bool, Vec<RenderNode>),
+    /// A checkbox or radio button form input (its type, and whether it's
+    /// checked), shown as the decorator's `form_input` text.
+    ///
+    /// ```rust
+    /// let html = "<p><input type=radio checked> AM <input type=radio> PM</p>";
+    /// assert_eq!(html2text::from_html(html, 80), "(•) AM ( ) PM\n");
+    /// ```
+    FormInput(String, bool),
+    /// A figure, with its content and captions
+    Figure(Vec<RenderNode>),
+    /// A figure caption
+    FigCaption(Vec<RenderNode>),
+    /// A table caption, which becomes part of its table; see
+    /// `RenderTable::set_caption`.
+    Caption(Vec<RenderNode>),
+    /// The attribution (`<footer>` or `<cite>`) of a blockquote, which is
+    /// right-aligned.
+    ///
+    /// ```rust
+    /// let html = b"<blockquote><p>Quote text</p><footer>Someone</footer></blockquote>";
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 30),
+    ///     "> Quote text\n> \n>                    — Someone\n"
+    /// );
+    /// ```
+    Attribution(Vec<RenderNode>),
+    /// A region with CSS vertical margins (blank lines above, below, children)
+    Margin(usize, usize, Vec<RenderNode>),
     /// A header (h1, h2, ...) with children
     Header(usize, Vec<RenderNode>),
-    /// A Div element with children
-    Div(Vec<RenderNode>),
-    /// A preformatted region.
-    Pre(Vec<RenderNode>),
+    /// A Div element with its alignment (right for `dir="rtl"`) and
+    /// children
+    Div(Alignment, Vec<RenderNode>),
+    /// A preformatted region.  Inline markup inside it, however deeply
+    /// nested, leaves the text and its whitespace alone.
+    ///
+    /// ```rust
+    /// let plain = "<pre>a  b\n  c</pre>";
+    /// let nested = format!(
+    ///     "<pre>{}a  b\n  c{}</pre>",
+    ///     "<span><b>".repeat(100),
+    ///     "</b></span>".repeat(100)
+    /// );
+    /// assert_eq!(html2text::from_html(plain, 80), "a  b\n  c\n");
+    /// assert_eq!(html2text::from_html(&nested, 80), html2text::from_html(plain, 80));
+    /// ```
+    ///
+    /// A `<br>` inside it starts a new line, as a newline would.
+    ///
+    /// ```rust
+    /// assert_eq!(html2text::from_html("<pre>line1<br>line2</pre>", 80), "line1\nline2\n");
+    /// assert_eq!(
+    ///     html2text::from_html("<pre>a   b<br>  c d<br><br>e</pre>", 80),
+    ///     "a   b\n  c d\n\ne\n"
+    /// );
+    /// ```
+    ///
+    /// The first field is the language of the code, from a
+    /// `class="language-..."` on the `<pre>` or a `<code>` inside it.  It's
+    /// added to the opening fence if one is set.
+    ///
+    /// ```rust
+    /// let html = br#"<pre><code class="language-rust">fn main() {}</code></pre>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "`fn main() {}`\n");
+    /// let text = html2text::config::plain().pre_fence("```").convert(&html[..]);
+    /// assert_eq!(text, "```rust\nfn main() {}\n```\n");
+    /// ```
+    Pre(Option<String>, Vec<RenderNode>),
     /// A blockquote
     BlockQuote(Vec<RenderNode>),
     /// An unordered list
     Ul(Vec<RenderNode>),
-    /// An ordered list
-    Ol(i64, Vec<RenderNode>),
+    /// An ordered list (start number, numbering style, reversed,
+    /// per-item `value` overrides, items)
+    ///
+    /// The prefixes are padded to the widest number in the list:
+    /// ```rust
+    /// let html = b"<ol start=\"9\"><li>a</li><li>b</li><li>c</li></ol>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "9.  a\n10. b\n11. c\n");
+    /// ```
+    ///
+    /// With `reversed` the items count down, from `start` if given or
+    /// else from the number of items:
+    /// ```rust
+    /// let html = b"<ol reversed><li>a</li><li>b</li><li>c</li></ol>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "3. a\n2. b\n1. c\n");
+    /// let html = b"<ol reversed start=\"10\"><li>a</li><li>b</li><li>c</li></ol>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "10. a\n9.  b\n8.  c\n");
+    /// ```
+    ///
+    /// An item's `value` renumbers it and the items after it:
+    /// ```rust
+    /// let html = b"<ol><li>a</li><li>b</li><li value=\"7\">c</li><li>d</li></ol>";
+    /// assert_eq!(html2text::from_read(&html[..], 80), "1. a\n2. b\n7. c\n8. d\n");
+    /// let html = b"<ol><li value=\"9223372036854775807\">a</li><li>b</li></ol>";
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 80),
+    ///     "9223372036854775807. a\n9223372036854775807. b\n"
+    /// );
+    /// let html = b"<ol reversed start=\"-9223372036854775808\"><li>a</li><li>b</li></ol>";
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 80),
+    ///     "-9223372036854775808. a\n-9223372036854775808. b\n"
+    /// );
+    /// ```
+    Ol(i64, OrderedListStyle, bool, Vec<Option<i64>>, Vec<RenderNode>),
     /// A description list (containing Dt or Dd)
     Dl(Vec<RenderNode>),
     /// A term (from a <dl>)
@@ -372,7 +993,56 @@ pub enum RenderNodeInfo {
     Dd(Vec<RenderNode>),
     /// A line break
     Break,
-    /// A table
+    /// A thematic break (`<hr>`), drawn only if the decorator has a
+    /// line for it.
+    HorizontalRule,
+    /// A point where a word may be wrapped, from `<wbr>`.
+    ///
+    /// ```rust
+    /// let html = b"<p>averylong<wbr>wordhere</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 12), "averylong\nwordhere\n");
+    /// assert_eq!(html2text::from_read(&html[..], 20), "averylongwordhere\n");
+    /// ```
+    WordBreakOpportunity,
+    /// The contents of an element styled `white-space: pre-wrap`, whose
+    /// text keeps its spaces and line breaks but is otherwise wrapped as
+    /// usual.
+    ///
+    /// ```rust
+    /// let html = br#"<div style="white-space: pre-wrap">one two three
+    /// four <a href="x">five</a>  six</div>"#;
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 9),
+    ///     "one two\nthree\nfour\n[five][1]\nsix\n\n[1]: x\n"
+    /// );
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 20),
+    ///     "one two three\nfour [five][1]  six\n\n[1]: x\n"
+    /// );
+    ///
+    /// let html = br#"<div style="white-space: pre-wrap"><table><tr><td>a</td><td>b</td></tr></table><script>alert(1)</script></div>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 20), "─┬─\na│b\n─┴─\n");
+    ///
+    /// let html = br#"<p style="white-space: pre-wrap"><em>two  spaces</em> <img src="i.png" alt="pic"></p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 40), "*two  spaces* [pic]\n");
+    /// ```
+    PreWrap(Vec<RenderNode>),
+    /// A table.  In Markdown a thematic break (`<hr>`) is a `---` block
+    /// of its own, while the header separator is part of a pipe table, so
+    /// the two can't be confused; the plain text output draws no `<hr>`.
+    ///
+    /// ```rust
+    /// let html = b"<p>above</p><hr><table><tr><th>a</th><th>b</th></tr>\
+    ///              <tr><td>1</td><td>2</td></tr></table><hr><p>below</p>";
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 20),
+    ///     "above\n\n─┬─\na│b\n─┼─\n1│2\n─┴─\n\nbelow\n"
+    /// );
+    /// assert_eq!(
+    ///     html2text::config::markdown().convert(&html[..]),
+    ///     "above\n\n---\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n---\n\nbelow\n"
+    /// );
+    /// ```
     Table(RenderTable),
     /// A set of table rows (from either <thead> or <tbody>
     TableBody(Vec<RenderTableRow>),
@@ -382,12 +1052,71 @@ pub enum RenderNodeInfo {
     TableRow(RenderTableRow, bool),
     /// Table cell (must only appear within a table row)
     TableCell(RenderTableCell),
-    /// Start of a named HTML fragment
+    /// Start of a named HTML fragment.  It takes up no space, so spaces
+    /// either side of it (or of an element starting one) are still
+    /// joined into one.
+    ///
+    /// ```rust
+    /// assert_eq!(html2text::from_read(&b"a <span> b</span>"[..], 80), "a b\n");
+    /// assert_eq!(html2text::from_read(&b"a <a id=\"f\"></a> b"[..], 80), "a b\n");
+    /// assert_eq!(html2text::from_read(&b"a <span id=\"g\"> b</span>"[..], 80), "a b\n");
+    /// ```
     FragStart(String),
     /// Section
     Section(Vec<RenderNode>),
-    /// Audio
-    Audio(String)
+    /// Text which isn't wrapped, from `<nobr>` or a `white-space: nowrap`
+    /// style.  It may overflow the width instead.  Rich output marks the
+    /// region with `NoBreakBegin` and `NoBreakEnd`.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = b"<p>see <nobr>the quite long phrase</nobr> here</p>";
+    /// assert_eq!(html2text::from_read(&html[..], 10), "see\nthe quite long phrase\nhere\n");
+    /// let html = br#"<p>see <span style="white-space: nowrap">a b c</span></p>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 6), "see\na b c\n");
+    ///
+    /// let lines = html2text::from_read_rich(&html[..], 6);
+    /// let tags: Vec<_> = lines[1].tagged_strings().map(|ts| ts.tag.clone()).collect();
+    /// assert_eq!(tags.first().unwrap(), &vec![RichAnnotation::NoBreakBegin]);
+    /// assert_eq!(tags.last().unwrap(), &vec![RichAnnotation::NoBreakEnd]);
+    /// ```
+    NoBreak(Vec<RenderNode>),
+    /// An `<audio>` element, with its source.  It's shown on a line of
+    /// its own, as `[audio: src]` in plain text and with a
+    /// `Custom("audio", [src])` annotation in rich output.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = br#"<p>Listen:</p><audio src="song.mp3"></audio>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "Listen:\n\n[audio: song.mp3]\n");
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// let audio = lines.last().unwrap().tagged_strings().next().unwrap();
+    /// assert_eq!(audio.tag, vec![RichAnnotation::Custom("audio".into(), vec!["song.mp3".into()])]);
+    ///
+    /// let html = br#"<audio controls><source src="a.ogg"><source src="a.mp3"></audio>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "[audio: a.ogg]\n");
+    ///
+    /// // The placeholder is wrapped like other text, and sized in tables.
+    /// let html = br#"<audio src="song.mp3"></audio>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 8), "[audio:\nsong.mp3\n]\n");
+    /// let html = br#"<table><tr><td><audio src="a.mp3"></td><td>b</td></tr></table>"#;
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 40),
+    ///     "──────────────┬─\n[audio: a.mp3]│b\n──────────────┴─\n"
+    /// );
+    /// ```
+    Audio(String),
+    /// A `<video>` element, with its source, shown like [`Audio`].
+    ///
+    /// [`Audio`]: RenderNodeInfo::Audio
+    ///
+    /// ```rust
+    /// let html = br#"<video src="clip.mp4"></video>"#;
+    /// assert_eq!(html2text::from_read(&html[..], 80), "[video: clip.mp4]\n");
+    /// ```
+    Video(String),
     // NonBreakStart
     // NonBreakStart,
     // NonBreakEnd
@@ -396,8 +1125,14 @@ pub enum RenderNodeInfo {
 
 /// Common fields from a node.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize),
+    serde(crate = "serde_crate")
+)]
 pub struct RenderNode {
-    size_estimate: Cell<Option<SizeEstimate>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    size_estimate: Cell<Option<(usize, SizeEstimate)>>,
     info: RenderNodeInfo,
 }
 
@@ -410,18 +1145,21 @@ impl RenderNode {
         }
     }
 
-    /// Get a size estimate
-    pub fn get_size_estimate(&self) -> SizeEstimate {
+    /// Get a size estimate, where no text needs to be narrower than
+    /// `min_width` columns.
+    pub fn get_size_estimate(&self, min_width: usize) -> SizeEstimate {
         // If it's already calculated, then just return the answer.
-        if let Some(s) = self.size_estimate.get() {
-            return s;
+        if let Some((m, s)) = self.size_estimate.get() {
+            if m == min_width {
+                return s;
+            }
         };
 
         use RenderNodeInfo::*;
 
         // Otherwise, make an estimate.
         let estimate = match self.info {
-            Text(ref t)  => {
+            Text(ref t) => {
                 use unicode_width::UnicodeWidthChar;
                 let mut len = 0;
                 let mut in_whitespace = false;
@@ -442,144 +1180,258 @@ impl RenderNode {
                 }
                 SizeEstimate {
                     size: len,
-                    min_width: len.min(MIN_WIDTH),
+                    min_width: len.min(min_width),
                 }
             }
             Img(_, _, img_w, img_h) => {
-                let len = img_w * img_h;
+                let len = img_w.saturating_mul(img_h);
                 SizeEstimate {
                     size: len,
-                    min_width: len.min(MIN_WIDTH),
+                    min_width: len.min(min_width),
                 }
             }
-            Container(ref v) | Em(ref v) | Strong(ref v) | Strikeout(ref v) | Code(ref v)
-            | Block(ref v) | Div(ref v) | Pre(ref v) | BlockQuote(ref v) | Dl(ref v)
-            | Dt(ref v) | Dd(ref v) | Colored(ref v,_ )| Redacted(ref v, _, _) | Section(ref v)=> v
+            Container(ref v) | Em(ref v) | Strong(ref v) | Strikeout(ref v) | Underline(ref v) | PreWrap(ref v) | Small(ref v) | Mark(ref v) | Code(ref v)
+            | Margin(_, _, ref v)
+            | Block(_, ref v) | TaskItem(_, ref v) | Figure(ref v) | FigCaption(ref v) | Caption(ref v) | Attribution(ref v) | Div(_, ref v) | Pre(_, ref v) | BlockQuote(ref v) | Dl(ref v)
+            | Dt(ref v) | Dd(ref v) | Colored(ref v,_ ) | BgColored(ref v, _) | Redacted(ref v, _, _) | Section(ref v)=> v
                 .iter()
-                .map(RenderNode::get_size_estimate)
+                .map(|node| node.get_size_estimate(min_width))
                 .fold(Default::default(), SizeEstimate::add),
-            Link(ref _target, ref v) => v
-                .iter()
-                .map(RenderNode::get_size_estimate)
-                .fold(Default::default(), SizeEstimate::add)
-                .add(SizeEstimate {
-                    size: 5,
-                    min_width: 5,
-                }),
-            Ul(ref v) => v
+            NoBreak(ref v) => {
+                let estimate = v
+                    .iter()
+                    .map(|node| node.get_size_estimate(min_width))
+                    .fold(SizeEstimate::default(), SizeEstimate::add);
+                // None of it can be wrapped.
+                SizeEstimate {
+                    size: estimate.size,
+                    min_width: estimate.size.max(estimate.min_width),
+                }
+            }
+            Quote(_, ref v) => v
                 .iter()
-                .map(RenderNode::get_size_estimate)
+                .map(|node| node.get_size_estimate(min_width))
                 .fold(Default::default(), SizeEstimate::add)
                 .add(SizeEstimate {
                     size: 2,
                     min_width: 2,
                 }),
-            Ol(i, ref v) => v
+            Citation(_, _, ref text) => SizeEstimate {
+                size: UnicodeWidthStr::width(text.as_str()) + 5,
+                min_width: 5,
+            },
+            // The default text, e.g. `[x]`.
+            FormInput(..) => SizeEstimate {
+                size: 3,
+                min_width: 3,
+            },
+            Link(ref _target, _, _, ref v) => v
                 .iter()
-                .map(RenderNode::get_size_estimate)
+                .map(|node| node.get_size_estimate(min_width))
                 .fold(Default::default(), SizeEstimate::add)
                 .add(SizeEstimate {
-                    size: i.to_string().len() + 2,
-                    min_width: i.to_string().len() + 2,
+                    size: 5,
+                    min_width: 5,
                 }),
+            Ul(ref v) => {
+                // The default prefixes: `[x] ` for task items, otherwise `* `.
+                let prefix_width = v
+                    .iter()
+                    .map(|item| match item.info {
+                        TaskItem(..) => 4,
+                        _ => 2,
+                    })
+                    .max()
+                    .unwrap_or(2);
+                v.iter()
+                    .map(|node| node.get_size_estimate(min_width))
+                    .fold(Default::default(), SizeEstimate::add)
+                    .add(SizeEstimate {
+                        size: prefix_width,
+                        min_width: prefix_width,
+                    })
+            }
+            Ol(start, style, reversed, ref values, ref v) => {
+                let prefix_width = ordered_list_numbers(start, reversed, values)
+                    .into_iter()
+                    .map(|i| list_marker(i, style).len())
+                    .max()
+                    .unwrap_or(0)
+                    + 2;
+                let content = v
+                    .iter()
+                    .map(|node| node.get_size_estimate(min_width))
+                    .fold(Default::default(), SizeEstimate::add);
+                // The prefix is beside every item, so it adds to the minimum width.
+                SizeEstimate {
+                    size: content.size + prefix_width,
+                    min_width: content.min_width + prefix_width,
+                }
+            }
             Header(level, ref v) => v
                 .iter()
-                .map(RenderNode::get_size_estimate)
+                .map(|node| node.get_size_estimate(min_width))
                 .fold(Default::default(), SizeEstimate::add)
                 .add(SizeEstimate {
                     size: 0,
-                    min_width: MIN_WIDTH + level + 2,
+                    min_width: min_width + level + 2,
                 }),
             Break => SizeEstimate {
                 size: 1,
                 min_width: 1,
             },
-            Table(ref t) => t.get_size_estimate(),
+            HorizontalRule => Default::default(),
+            Table(ref t) => t.get_size_estimate(min_width),
             TableRow(..) | TableBody(_) | TableCell(_) => unimplemented!(),
-            FragStart(_) => Default::default(),
-            Audio(_) => Default::default() ,
+            FragStart(_) | WordBreakOpportunity => Default::default(),
+            Audio(ref src) | Video(ref src) => {
+                // The placeholder is something like "[audio: src]".
+                let len = UnicodeWidthStr::width(src.as_str()) + 9;
+                SizeEstimate {
+                    size: len,
+                    min_width: len.min(min_width),
+                }
+            }
         };
-        self.size_estimate.set(Some(estimate));
+        self.size_estimate.set(Some((min_width, estimate)));
         estimate
     }
 
     /// Return true if this node is definitely empty.  This is used to quickly
     /// remove e.g. links with no anchor text in most cases, but can't recurse
     /// and look more deeply.
+    ///
+    /// ```rust
+    /// use html2text::{RenderNode, RenderNodeInfo};
+    ///
+    /// let image = |w, h| RenderNode::new(RenderNodeInfo::Img("a.png".into(), Some("A".into()), w, h));
+    /// assert!(image(0, 10).is_shallow_empty());
+    /// assert!(!image(99_999_999_999, 99_999_999_999).is_shallow_empty());
+    /// ```
     pub fn is_shallow_empty(&self) -> bool {
         use RenderNodeInfo::*;
 
         // Otherwise, make an estimate.
         match self.info {
-            Text(ref t)  => {
+            Text(ref t) => {
                 let len = t.trim().len();
                 len == 0
             }
             Img(_, _, w , h)=>{
-                w * h == 0
+                w == 0 || h == 0
             }
+            Citation(..) | FormInput(..) => false,
             Container(ref v)
-            | Link(_, ref v)
+            | Link(_, _, _, ref v)
             | Em(ref v)
             | Strong(ref v)
             | Strikeout(ref v)
+            | Underline(ref v)
+            | PreWrap(ref v)
+            | Small(ref v)
+            | Mark(ref v)
+            | Quote(_, ref v)
+            | Margin(_, _, ref v)
             | Code(ref v)
-            | Block(ref v)
-            | Div(ref v)
-            | Pre(ref v)
+            | Block(_, ref v)
+            | TaskItem(_, ref v)
+            | Figure(ref v)
+            | FigCaption(ref v)
+            | Caption(ref v)
+            | Attribution(ref v)
+            | Div(_, ref v)
+            | Pre(_, ref v)
             | BlockQuote(ref v)
             | Dl(ref v)
             | Dt(ref v)
             | Dd(ref v)
             | Ul(ref v)
-            | Ol(_, ref v) => v.is_empty(),
+            | Ol(_, _, _, _, ref v) => v.is_empty(),
             Header(_level, ref v) => v.is_empty(),
-            Break => true,
+            Break | HorizontalRule => true,
             Table(ref _t) => false,
             TableRow(..) | TableBody(_) | TableCell(_) => false,
-            FragStart(_) => true,
-            Colored(ref v,_ ) => v.is_empty(),
+            FragStart(_) | WordBreakOpportunity => true,
+            Colored(ref v,_ ) | BgColored(ref v, _) => v.is_empty(),
             Redacted(ref v , _, _) => v.is_empty(),
-            Section(ref v) => v.is_empty(),
-            Audio(_) => false,
+            Section(ref v) | NoBreak(ref v) => v.is_empty(),
+            Audio(_) | Video(_) => false,
             
         }
     }
 }
 
-fn precalc_size_estimate<'a>(node: &'a RenderNode) -> TreeMapResult<(), &'a RenderNode, ()> {
+/// Work out the size estimates of `tree` from the leaves up, so that
+/// working them out later doesn't recurse deeply.  Only the first
+/// `max_nodes` nodes are visited, if given, as no more will be rendered.
+fn precalc_size_estimates(tree: &RenderNode, min_width: usize, max_nodes: Option<usize>) {
+    let mut budget = max_nodes;
+    tree_map_reduce(&mut budget, tree, |budget, node| {
+        match *budget {
+            Some(0) => return TreeMapResult::Nothing,
+            Some(ref mut remaining) => *remaining -= 1,
+            None => {}
+        }
+        precalc_size_estimate(node, min_width)
+    });
+}
+
+/// Work out the size estimate of `node` once those of its children are
+/// known.  Nodes whose children weren't all visited before the budget ran
+/// out are left alone.
+fn precalc_size_estimate<'a>(
+    node: &'a RenderNode,
+    min_width: usize,
+) -> TreeMapResult<'a, Option<usize>, &'a RenderNode, ()> {
     use RenderNodeInfo::*;
-    if node.size_estimate.get().is_some() {
-        return TreeMapResult::Nothing;
+    if let Some((m, _)) = node.size_estimate.get() {
+        if m == min_width {
+            return TreeMapResult::Nothing;
+        }
     }
     match node.info {
-        Text(_) | Img(_, _, _, _) | Break | FragStart(_) => {
-            let _ = node.get_size_estimate();
+        Text(_) | Img(_, _, _, _) | Citation(..) | FormInput(..) | Break | HorizontalRule | WordBreakOpportunity | FragStart(_) => {
+            let _ = node.get_size_estimate(min_width);
             TreeMapResult::Nothing
         }
 
         Container(ref v)
-        | Link(_, ref v)
+        | Link(_, _, _, ref v)
         | Em(ref v)
         | Strong(ref v)
         | Strikeout(ref v)
+        | Underline(ref v)
+        | PreWrap(ref v)
+        | Small(ref v)
+        | Mark(ref v)
+        | Quote(_, ref v)
+        | Margin(_, _, ref v)
         | Colored(ref v,_ )
+        | BgColored(ref v, _)
         | Section(ref v)
+        | NoBreak(ref v)
         | Redacted(ref v, _, _)
         | Code(ref v)
-        | Block(ref v)
-        | Div(ref v)
-        | Pre(ref v)
+        | Block(_, ref v)
+        | TaskItem(_, ref v)
+        | Figure(ref v)
+        | FigCaption(ref v)
+        | Caption(ref v)
+        | Attribution(ref v)
+        | Div(_, ref v)
+        | Pre(_, ref v)
         | BlockQuote(ref v)
         | Ul(ref v)
-        | Ol(_, ref v)
+        | Ol(_, _, _, _, ref v)
         | Dl(ref v)
         | Dt(ref v)
         | Dd(ref v)
         | Header(_, ref v) => TreeMapResult::PendingChildren {
             children: v.iter().collect(),
-            cons: Box::new(move |_, _cs| {
-                node.get_size_estimate();
+            cons: Box::new(move |budget: &mut Option<usize>, _cs| {
+                if *budget != Some(0) {
+                    node.get_size_estimate(min_width);
+                }
                 None
             }),
             prefn: None,
@@ -587,7 +1439,7 @@ fn precalc_size_estimate<'a>(node: &'a RenderNode) -> TreeMapResult<(), &'a Rend
         },
         Table(ref t) => {
             /* Return all the indirect children which are RenderNodes. */
-            let mut children = Vec::new();
+            let mut children: Vec<_> = t.caption.iter().collect();
             for row in &t.rows {
                 for cell in &row.cells {
                     children.extend(cell.content.iter());
@@ -595,8 +1447,10 @@ fn precalc_size_estimate<'a>(node: &'a RenderNode) -> TreeMapResult<(), &'a Rend
             }
             TreeMapResult::PendingChildren {
                 children,
-                cons: Box::new(move |_, _cs| {
-                    node.get_size_estimate();
+                cons: Box::new(move |budget: &mut Option<usize>, _cs| {
+                    if *budget != Some(0) {
+                        node.get_size_estimate(min_width);
+                    }
                     None
                 }),
                 prefn: None,
@@ -604,32 +1458,88 @@ fn precalc_size_estimate<'a>(node: &'a RenderNode) -> TreeMapResult<(), &'a Rend
             }
         }
         TableRow(..) | TableBody(_) | TableCell(_) => unimplemented!(),
-        Audio(_) => TreeMapResult::Nothing,
+        Audio(_) | Video(_) => TreeMapResult::Nothing,
     }
 }
 
 /// Make a Vec of RenderNodes from the children of a node.
-fn children_to_render_nodes<T: Write>(handle: Handle, err_out: &mut T) -> Vec<RenderNode> {
-    /* process children, but don't add anything */
-    let children = handle
-        .children
-        .borrow()
-        .iter()
-        .flat_map(|ch| dom_to_render_tree(ch.clone(), err_out))
-        .collect();
-    children
+fn children_to_render_nodes<T: Write>(
+    handle: &Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
+    err_out: &mut T,
+) -> Vec<RenderNode> {
+    children_except_to_render_nodes(handle, None, base_url, keep_hidden, err_out)
 }
 
-/// Make a Vec of RenderNodes from the <li> children of a node.
-fn list_children_to_render_nodes<T: Write>(handle: Handle, err_out: &mut T) -> Vec<RenderNode> {
+/// Make a Vec of RenderNodes from the children of a node, leaving out the
+/// child at index `skip` if given.  The children are moved out of the node
+/// while they're converted rather than cloned, and put back afterwards.
+fn children_except_to_render_nodes<T: Write>(
+    handle: &Handle,
+    skip: Option<usize>,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
+    err_out: &mut T,
+) -> Vec<RenderNode> {
+    let children = std::mem::take(&mut *handle.children.borrow_mut());
+    let mut converted = Vec::with_capacity(children.len());
+    let mut result = Vec::new();
+    for (i, child) in children.into_iter().enumerate() {
+        if Some(i) == skip {
+            converted.push(child);
+        } else {
+            let (node, child) = convert_dom_tree(child, base_url, keep_hidden, err_out);
+            result.extend(node);
+            converted.push(child);
+        }
+    }
+    *handle.children.borrow_mut() = converted;
+    result
+}
+
+/// Make a Vec of RenderNodes from the <li> children of a node, along with
+/// each item's `value` attribute (if any).
+fn list_children_to_render_nodes<T: Write>(
+    handle: &Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
+    err_out: &mut T,
+) -> Vec<(Option<i64>, RenderNode)> {
     let mut children = Vec::new();
 
     for child in handle.children.borrow().iter() {
         match child.data {
-            Element { ref name, .. } => match name.expanded() {
-                expanded_name!(html "li") => {
-                    let li_children = children_to_render_nodes(child.clone(), err_out);
-                    children.push(RenderNode::new(RenderNodeInfo::Block(li_children)));
+            Element {
+                ref name,
+                ref attrs,
+                ..
+            } => match name.expanded() {
+                expanded_name!(html "li") if keep_hidden || !is_hidden(child) => {
+                    let value = attrs
+                        .borrow()
+                        .iter()
+                        .find(|attr| &attr.name.local == "value")
+                        .and_then(|attr| attr.value.trim().parse().ok());
+                    let item = match leading_checkbox(child) {
+                        Some((idx, checked)) => {
+                            // The checkbox becomes the item's prefix, so
+                            // leave it out of the contents.
+                            let li_children = children_except_to_render_nodes(
+                                child,
+                                Some(idx),
+                                base_url,
+                                keep_hidden,
+                                err_out,
+                            );
+                            RenderNodeInfo::TaskItem(checked, li_children)
+                        }
+                        None => RenderNodeInfo::Block(
+                            Alignment::Left,
+                            children_to_render_nodes(child, base_url, keep_hidden, err_out),
+                        ),
+                    };
+                    children.push((value, RenderNode::new(item)));
                 }
                 _ => {}
             },
@@ -642,9 +1552,170 @@ fn list_children_to_render_nodes<T: Write>(handle: Handle, err_out: &mut T) -> V
     children
 }
 
+/// If the first non-blank child of a list item is a checkbox `<input>`,
+/// return its index and whether it is checked.
+fn leading_checkbox(handle: &Handle) -> Option<(usize, bool)> {
+    for (idx, child) in handle.children.borrow().iter().enumerate() {
+        match child.data {
+            markup5ever_rcdom::NodeData::Text { ref contents } if contents.borrow().trim().is_empty() => continue,
+            Comment { .. } => continue,
+            Element {
+                ref name,
+                ref attrs,
+                ..
+            } if name.expanded() == expanded_name!(html "input") => {
+                let attrs = attrs.borrow();
+                let is_checkbox = attrs.iter().any(|attr| {
+                    &attr.name.local == "type" && attr.value.eq_ignore_ascii_case("checkbox")
+                });
+                if !is_checkbox {
+                    return None;
+                }
+                return Some((idx, attrs.iter().any(|attr| &attr.name.local == "checked")));
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Return the text inside a node, with whitespace collapsed.
+fn text_content(handle: &Handle) -> String {
+    fn collect(handle: &Handle, text: &mut String) {
+        for child in handle.children.borrow().iter() {
+            match child.data {
+                markup5ever_rcdom::NodeData::Text { ref contents } => {
+                    text.push_str(&contents.borrow());
+                    text.push(' ');
+                }
+                Element { .. } => collect(child, text),
+                _ => {}
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(handle, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Return the language named by a `language-xxx` (or `lang-xxx`) class on
+/// a `<pre>` element or the first `<code>` element inside it.
+fn pre_language(handle: &Handle) -> Option<String> {
+    fn class_language(handle: &Handle) -> Option<String> {
+        if let Element { ref attrs, .. } = handle.data {
+            for attr in attrs.borrow().iter() {
+                if &attr.name.local == "class" {
+                    let language = attr.value.split_whitespace().find_map(|class| {
+                        class
+                            .strip_prefix("language-")
+                            .or_else(|| class.strip_prefix("lang-"))
+                    });
+                    if let Some(language) = language.filter(|l| !l.is_empty()) {
+                        return Some(language.to_string());
+                    }
+                }
+            }
+        }
+        None
+    }
+    class_language(handle).or_else(|| {
+        let children = handle.children.borrow();
+        let code = children.iter().find(|child| match child.data {
+            Element { ref name, .. } => name.expanded() == expanded_name!(html "code"),
+            _ => false,
+        })?;
+        class_language(code)
+    })
+}
+
+/// Return the source of an `<audio>` or `<video>` element: its `src`, or
+/// else that of its first `<source>` child.
+fn media_source(handle: &Handle) -> Option<String> {
+    fn src(handle: &Handle) -> Option<String> {
+        match handle.data {
+            Element { ref attrs, .. } => attrs
+                .borrow()
+                .iter()
+                .find(|attr| &attr.name.local == "src" && !attr.value.is_empty())
+                .map(|attr| attr.value.to_string()),
+            _ => None,
+        }
+    }
+    src(handle).or_else(|| {
+        handle
+            .children
+            .borrow()
+            .iter()
+            .filter(|child| match child.data {
+                Element { ref name, .. } => name.expanded() == expanded_name!(html "source"),
+                _ => false,
+            })
+            .find_map(src)
+    })
+}
+
+/// Return whether a node's parent is a `<blockquote>`.
+fn parent_is_blockquote(handle: &Handle) -> bool {
+    let parent = handle.parent.take();
+    let result = match parent.as_ref().and_then(|weak| weak.upgrade()) {
+        Some(ref node) => match node.data {
+            Element { ref name, .. } => name.expanded() == expanded_name!(html "blockquote"),
+            _ => false,
+        },
+        None => false,
+    };
+    handle.parent.set(parent);
+    result
+}
+
+/// Render a `<select>` as its list of options, e.g. `[ one | *two | three ]`,
+/// with the selected option(s) marked with `*`.
+///
+/// ```rust
+/// let html = b"<select><option>one</option><option selected>two</option></select>";
+/// assert_eq!(html2text::from_read(&html[..], 80), "[ one | *two ]\n");
+/// ```
+fn select_to_text(handle: &Handle, multiple: bool) -> String {
+    fn collect(handle: &Handle, options: &mut Vec<(String, bool)>) {
+        for child in handle.children.borrow().iter() {
+            if let Element {
+                ref name,
+                ref attrs,
+                ..
+            } = child.data
+            {
+                match name.expanded() {
+                    expanded_name!(html "option") => {
+                        let selected = attrs
+                            .borrow()
+                            .iter()
+                            .any(|attr| &attr.name.local == "selected");
+                        options.push((text_content(child), selected));
+                    }
+                    expanded_name!(html "optgroup") => collect(child, options),
+                    _ => {}
+                }
+            }
+        }
+    }
+    let mut options = Vec::new();
+    collect(handle, &mut options);
+    // Without an explicit choice, a drop-down shows its first option.
+    if !multiple && !options.is_empty() && !options.iter().any(|&(_, selected)| selected) {
+        options[0].1 = true;
+    }
+    let choices: Vec<String> = options
+        .into_iter()
+        .map(|(text, selected)| if selected { format!("*{}", text) } else { text })
+        .collect();
+    format!("[ {} ]", choices.join(" | "))
+}
+
 /// Make a Vec of DtElements from the <dt> and <dd> children of a node.
 fn desc_list_children_to_render_nodes<T: Write>(
-    handle: Handle,
+    handle: &Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
     err_out: &mut T,
 ) -> Vec<RenderNode> {
     let mut children = Vec::new();
@@ -652,12 +1723,12 @@ fn desc_list_children_to_render_nodes<T: Write>(
     for child in handle.children.borrow().iter() {
         match child.data {
             Element { ref name, .. } => match name.expanded() {
-                expanded_name!(html "dt") => {
-                    let dt_children = children_to_render_nodes(child.clone(), err_out);
+                expanded_name!(html "dt") if keep_hidden || !is_hidden(child) => {
+                    let dt_children = children_to_render_nodes(child, base_url, keep_hidden, err_out);
                     children.push(RenderNode::new(RenderNodeInfo::Dt(dt_children)));
                 }
-                expanded_name!(html "dd") => {
-                    let dd_children = children_to_render_nodes(child.clone(), err_out);
+                expanded_name!(html "dd") if keep_hidden || !is_hidden(child) => {
+                    let dd_children = children_to_render_nodes(child, base_url, keep_hidden, err_out);
                     children.push(RenderNode::new(RenderNodeInfo::Dd(dd_children)));
                 }
                 _ => {}
@@ -673,38 +1744,142 @@ fn desc_list_children_to_render_nodes<T: Write>(
 
 /// Convert a table into a RenderNode
 fn table_to_render_tree<'a, 'b, T: Write>(
-    handle: Handle,
+    handle: &Handle,
     _err_out: &'b mut T,
 ) -> TreeMapResult<'a, (), Handle, RenderNode> {
-    pending(handle, |_, rowset| {
+    let mut borderless = false;
+    if let Element { ref attrs, .. } = handle.data {
+        for attr in attrs.borrow().iter() {
+            if &attr.name.local == "border" {
+                borderless = attr.value.trim().parse() == Ok(0);
+            }
+        }
+    }
+    let column_weights = column_width_hints(handle);
+    pending(handle, move |_, rowset| {
         let mut rows = vec![];
+        let mut caption = vec![];
         for bodynode in rowset {
-            if let RenderNodeInfo::TableBody(body) = bodynode.info {
-                rows.extend(body);
-            } else {
-                html_trace!("Found in table: {:?}", bodynode.info);
+            match bodynode.info {
+                RenderNodeInfo::TableBody(body) => rows.extend(body),
+                RenderNodeInfo::Caption(cs) => caption.extend(cs),
+                _ => {
+                    html_trace!("Found in table: {:?}", bodynode.info);
+                }
             }
         }
-        Some(RenderNode::new(RenderNodeInfo::Table(RenderTable::new(
-            rows,
-        ))))
+        let mut table = RenderTable::new(rows);
+        table.set_borderless(borderless);
+        table.set_column_weights(column_weights.clone());
+        table.set_caption(caption);
+        Some(RenderNode::new(RenderNodeInfo::Table(table)))
     })
 }
 
-/// Add rows from a thead or tbody.
-fn tbody_to_render_tree<'a, 'b, T: Write>(
-    handle: Handle,
-    _err_out: &'b mut T,
-) -> TreeMapResult<'a, (), Handle, RenderNode> {
-    pending(handle, |_, rowchildren| {
-        let rows = rowchildren
-            .into_iter()
-            .flat_map(|rownode| {
-                if let RenderNodeInfo::TableRow(row, _) = rownode.info {
-                    Some(row)
-                } else {
-                    html_trace!("  [[tbody child: {:?}]]", rownode);
-                    None
+/// The most columns `<col>` and `<colgroup>` elements can describe.
+const MAX_COLUMN_HINTS: usize = 1000;
+
+/// The largest relative weight a column width hint can have.
+const MAX_COLUMN_WEIGHT: usize = 10_000;
+
+/// Parse a `width` attribute of a `<col>` or `<colgroup>`, either in
+/// pixels or as a percentage, into a relative weight and whether it was
+/// a percentage.
+fn parse_column_width(value: &str) -> Option<(usize, bool)> {
+    let value = value.trim();
+    let (value, percent) = match value.strip_suffix('%') {
+        Some(value) => (value, true),
+        None => (value.strip_suffix("px").unwrap_or(value), false),
+    };
+    value
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|&w| w > 0)
+        .map(|w| (w.min(MAX_COLUMN_WEIGHT), percent))
+}
+
+/// Return the `width` and `span` attributes of a `<col>` or `<colgroup>`.
+fn column_attrs(handle: &Handle) -> (Option<(usize, bool)>, usize) {
+    let mut width = None;
+    let mut span = 1;
+    if let Element { ref attrs, .. } = handle.data {
+        for attr in attrs.borrow().iter() {
+            if &attr.name.local == "width" {
+                width = parse_column_width(&attr.value);
+            } else if &attr.name.local == "span" {
+                span = attr.value.trim().parse().unwrap_or(1).clamp(1, MAX_COLUMN_HINTS);
+            }
+        }
+    }
+    (width, span)
+}
+
+/// Collect the column width hints from a table's `<colgroup>` and `<col>`
+/// elements, one for each column.
+fn column_width_hints(table: &Handle) -> Vec<Option<usize>> {
+    let is_element = |handle: &Handle, wanted: &str| match handle.data {
+        Element { ref name, .. } => &name.local == wanted,
+        _ => false,
+    };
+    let mut hints = Vec::new();
+    for group in table.children.borrow().iter() {
+        if !is_element(group, "colgroup") {
+            continue;
+        }
+        let cols: Vec<Handle> = group
+            .children
+            .borrow()
+            .iter()
+            .filter(|child| is_element(child, "col"))
+            .cloned()
+            .collect();
+        if cols.is_empty() {
+            let (width, span) = column_attrs(group);
+            hints.extend(repeat(width).take(span));
+        } else {
+            for col in cols {
+                let (width, span) = column_attrs(&col);
+                hints.extend(repeat(width).take(span));
+            }
+        }
+    }
+    hints.truncate(MAX_COLUMN_HINTS);
+    // Pixels and percentages can't be compared without knowing the
+    // table's width, so mixed hints are ignored.
+    let percent = hints.iter().flatten().map(|&(_, percent)| percent);
+    if percent.clone().any(|p| p) && percent.clone().any(|p| !p) {
+        return Vec::new();
+    }
+    hints
+        .into_iter()
+        .map(|hint| hint.map(|(width, _)| width))
+        .collect()
+}
+
+/// Add rows from a thead or tbody.
+fn tbody_to_render_tree<'a, 'b, T: Write>(
+    handle: &Handle,
+    _err_out: &'b mut T,
+) -> TreeMapResult<'a, (), Handle, RenderNode> {
+    let (header, footer) = match handle.data {
+        Element { ref name, .. } => (
+            name.expanded() == expanded_name!(html "thead"),
+            name.expanded() == expanded_name!(html "tfoot"),
+        ),
+        _ => (false, false),
+    };
+    pending(handle, move |_, rowchildren| {
+        let rows = rowchildren
+            .into_iter()
+            .flat_map(|rownode| {
+                if let RenderNodeInfo::TableRow(mut row, _) = rownode.info {
+                    row.header = header;
+                    row.footer = footer;
+                    Some(row)
+                } else {
+                    html_trace!("  [[tbody child: {:?}]]", rownode);
+                    None
                 }
             })
             .collect();
@@ -714,7 +1889,7 @@ fn tbody_to_render_tree<'a, 'b, T: Write>(
 
 /// Convert a table row to a RenderTableRow
 fn tr_to_render_tree<'a, 'b, T: Write>(
-    handle: Handle,
+    handle: &Handle,
     _err_out: &'b mut T,
 ) -> TreeMapResult<'a, (), Handle, RenderNode> {
     pending(handle, |_, cellnodes| {
@@ -730,10 +1905,7 @@ fn tr_to_render_tree<'a, 'b, T: Write>(
             })
             .collect();
         Some(RenderNode::new(RenderNodeInfo::TableRow(
-            RenderTableRow {
-                cells,
-                col_sizes: None,
-            },
+            RenderTableRow::new(cells),
             false,
         )))
     })
@@ -741,11 +1913,18 @@ fn tr_to_render_tree<'a, 'b, T: Write>(
 
 /// Convert a single table cell to a render node.
 fn td_to_render_tree<'a, 'b, T: Write>(
-    handle: Handle,
+    handle: &Handle,
     _err_out: &'b mut T,
 ) -> TreeMapResult<'a, (), Handle, RenderNode> {
     let mut colspan = 1;
-    if let Element { ref attrs, .. } = handle.data {
+    let mut header = false;
+    if let Element {
+        ref name,
+        ref attrs,
+        ..
+    } = handle.data
+    {
+        header = name.expanded() == expanded_name!(html "th");
         for attr in attrs.borrow().iter() {
             if &attr.name.local == "colspan" {
                 let v: &str = &*attr.value;
@@ -760,6 +1939,7 @@ fn td_to_render_tree<'a, 'b, T: Write>(
                 content: children,
                 size_estimate: Cell::new(None),
                 col_width: None,
+                header,
             },
         )))
     })
@@ -880,29 +2060,428 @@ where
 }
 
 /// Convert a DOM tree or subtree into a render tree.
+///
+/// Comments are always discarded along with their text, including
+/// Internet Explorer conditional comments, and don't separate the text
+/// either side of them.
+///
+/// ```rust
+/// assert_eq!(html2text::from_read(&b"<p>a<!-- hidden -->b</p>"[..], 80), "ab\n");
+/// assert_eq!(html2text::from_read(&b"<p>a <!-- hidden --> b</p>"[..], 80), "a b\n");
+/// assert_eq!(
+///     html2text::from_read(&b"<p><!--[if IE]>old<![endif]-->new</p>"[..], 80),
+///     "new\n"
+/// );
+/// ```
+///
+/// Elements hidden with the `hidden` attribute or `display: none` are
+/// dropped in the same way.
+///
+/// ```rust
+/// let html = br#"<p>a <span style="display:none">skip</span> b<span hidden>c</span></p>"#;
+/// assert_eq!(html2text::from_read(&html[..], 80), "a b\n");
+/// let html = b"<ul><li>one</li><li hidden>two</li></ul>";
+/// assert_eq!(html2text::from_read(&html[..], 80), "* one\n");
+/// ```
 pub fn dom_to_render_tree<T: Write>(handle: Handle, err_out: &mut T) -> Option<RenderNode> {
+    dom_to_render_tree_with_base(handle, None, false, err_out)
+}
+
+/// Like `dom_to_render_tree`, but resolving relative link and image URLs
+/// against `base_url` if given, and keeping hidden elements if
+/// `keep_hidden` is set.
+fn dom_to_render_tree_with_base<T: Write>(
+    handle: Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
+    err_out: &mut T,
+) -> Option<RenderNode> {
+    convert_dom_tree(handle, base_url, keep_hidden, err_out).0
+}
+
+/// Convert `handle` into a render tree, returning it along with `handle`.
+///
+/// Rather than cloning each child `Handle`, a node's children are moved
+/// out of it while they're being converted.  The converted nodes are kept
+/// in `done`, one list per level of nesting, so that they can be put back
+/// once their parent is finished, leaving the DOM as it was.
+fn convert_dom_tree<T: Write>(
+    handle: Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
+    err_out: &mut T,
+) -> (Option<RenderNode>, Handle) {
     html_trace!("### dom_to_render_tree: HTML: {:?}", handle);
-    let result = tree_map_reduce(&mut (), handle, |_, handle| {
-        process_dom_node(handle, err_out)
+    let mut done: Vec<Vec<Handle>> = vec![Vec::new()];
+    let result = tree_map_reduce(&mut done, handle, |done, handle| {
+        let result = process_dom_node(&handle, base_url, keep_hidden, err_out);
+        done.last_mut().unwrap().push(handle);
+        match result {
+            TreeMapResult::Finished(node) => TreeMapResult::Finished(node),
+            TreeMapResult::Nothing => TreeMapResult::Nothing,
+            TreeMapResult::PendingChildren {
+                children,
+                cons,
+                prefn,
+                postfn,
+            } => {
+                done.push(Vec::with_capacity(children.len()));
+                TreeMapResult::PendingChildren {
+                    children,
+                    cons: Box::new(move |done: &mut Vec<Vec<Handle>>, cs| {
+                        let children = done.pop().unwrap();
+                        let parent = done.last().unwrap().last().unwrap();
+                        *parent.children.borrow_mut() = children;
+                        cons(&mut (), cs)
+                    }),
+                    prefn: prefn.map(|f| {
+                        Box::new(move |_: &mut Vec<Vec<Handle>>, h: &Handle| f(&mut (), h))
+                            as Box<ChildPreFn<_, _>>
+                    }),
+                    postfn: postfn.map(|f| {
+                        Box::new(move |_: &mut Vec<Vec<Handle>>, r: &RenderNode| f(&mut (), r))
+                            as Box<ChildPostFn<_, _>>
+                    }),
+                }
+            }
+        }
     });
 
     html_trace!("### dom_to_render_tree: out= {:#?}", result);
-    result
+    (result, done.pop().unwrap().pop().unwrap())
+}
+
+/// The text the decorator is given for an image with no `alt` attribute.
+pub(crate) const NO_ALT_TEXT: &str = "No Alt Text Provided";
+
+/// Resolve `url` against `base_url`, if there is one.  Links to a fragment
+/// of this document are left alone, so that they can still be followed.
+fn resolve_url(base_url: Option<&Url>, url: &str) -> String {
+    match base_url {
+        Some(base) if !url.starts_with('#') => match base.join(url) {
+            Ok(resolved) => resolved.into(),
+            Err(_) => url.to_string(),
+        },
+        _ => url.to_string(),
+    }
+}
+
+/// Return the `href` of the first `<base>` element in the document's
+/// `<head>`, if any.  This runs before deep nesting is flattened, so it
+/// searches with an explicit stack rather than recursing.
+fn find_base_href(document: &Handle) -> Option<String> {
+    fn child_named(node: &Handle, local: &str) -> Option<Handle> {
+        node.children
+            .borrow()
+            .iter()
+            .find(|child| match child.data {
+                Element { ref name, .. } => {
+                    name.ns == ns!(html) && &name.local == local
+                }
+                _ => false,
+            })
+            .cloned()
+    }
+    let html = child_named(document, "html")?;
+    let head = child_named(&html, "head")?;
+    let mut stack = vec![head];
+    while let Some(node) = stack.pop() {
+        if let Element {
+            ref name,
+            ref attrs,
+            ..
+        } = node.data
+        {
+            if name.expanded() == expanded_name!(html "base") {
+                let href = attrs
+                    .borrow()
+                    .iter()
+                    .find(|attr| &attr.name.local == "href")
+                    .map(|attr| attr.value.to_string());
+                if href.is_some() {
+                    return href;
+                }
+            }
+        }
+        stack.extend(node.children.borrow().iter().rev().cloned());
+    }
+    None
+}
+
+/// How deeply elements can be nested before their contents are flattened.
+const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Replace the children of every node `max_depth` levels down with the
+/// leaf nodes (text, images, etc.) beneath it, in document order.  Parts
+/// of the conversion and rendering recurse over the tree, so this stops
+/// pathologically deep documents from overflowing the stack, at the cost
+/// of losing the markup below that depth.
+fn flatten_deep_nodes(handle: &Handle, max_depth: usize) {
+    let mut stack = vec![(handle.clone(), 0)];
+    while let Some((node, depth)) = stack.pop() {
+        if depth < max_depth {
+            stack.extend(
+                node.children
+                    .borrow()
+                    .iter()
+                    .map(|child| (child.clone(), depth + 1)),
+            );
+            continue;
+        }
+        let mut leaves = Vec::new();
+        let mut pending: Vec<Handle> = node.children.take().into_iter().rev().collect();
+        while let Some(descendant) = pending.pop() {
+            let children = descendant.children.take();
+            if children.is_empty() {
+                descendant.parent.set(Some(Rc::downgrade(&node)));
+                leaves.push(descendant);
+            } else {
+                pending.extend(children.into_iter().rev());
+            }
+        }
+        *node.children.borrow_mut() = leaves;
+    }
 }
 
-fn pending<'a, F>(handle: Handle, f: F) -> TreeMapResult<'a, (), Handle, RenderNode>
+fn pending<'a, F>(handle: &Handle, f: F) -> TreeMapResult<'a, (), Handle, RenderNode>
 where
     //for<'a> F: Fn(&'a mut C, Vec<RenderNode>) -> Option<RenderNode>+'static
     for<'r> F: Fn(&'r mut (), std::vec::Vec<RenderNode>) -> Option<RenderNode> + 'static,
 {
     TreeMapResult::PendingChildren {
-        children: handle.children.borrow().clone(),
+        children: std::mem::take(&mut *handle.children.borrow_mut()),
         cons: Box::new(f),
         prefn: None,
         postfn: None,
     }
 }
 
+/// Wrap the result of processing a DOM node (which may still be waiting
+/// for its children) in a new node made by `wrap`.
+fn wrap_result<'a, F>(
+    result: TreeMapResult<'a, (), Handle, RenderNode>,
+    wrap: F,
+) -> TreeMapResult<'a, (), Handle, RenderNode>
+where
+    F: Fn(Vec<RenderNode>) -> RenderNodeInfo + 'static,
+{
+    use TreeMapResult::*;
+    match result {
+        Finished(node) => Finished(RenderNode::new(wrap(vec![node]))),
+        Nothing => Nothing,
+        PendingChildren {
+            children,
+            cons,
+            prefn,
+            postfn,
+        } => PendingChildren {
+            children,
+            prefn,
+            postfn,
+            cons: Box::new(move |ctx, ch| {
+                cons(ctx, ch).map(|node| RenderNode::new(wrap(vec![node])))
+            }),
+        },
+    }
+}
+
+/// Return the alignment given by an element's `text-align` style or
+/// `align` attribute, or right alignment for `dir="rtl"`.
+fn block_alignment(handle: &Handle) -> Alignment {
+    let parse = |value: &str| match value.trim().to_ascii_lowercase().as_str() {
+        "center" => Some(Alignment::Center),
+        "right" => Some(Alignment::Right),
+        "left" => Some(Alignment::Left),
+        _ => None,
+    };
+    let mut alignment = None;
+    if let Element { ref attrs, .. } = handle.data {
+        for attr in attrs.borrow().iter() {
+            if &attr.name.local == "style" {
+                for (name, value) in parse_style(&attr.value) {
+                    if name == "text-align" {
+                        alignment = parse(&value).or(alignment);
+                    }
+                }
+            } else if &attr.name.local == "align" && alignment.is_none() {
+                alignment = parse(&attr.value);
+            }
+        }
+    }
+    match alignment {
+        Some(alignment) => alignment,
+        None if is_rtl(handle) => Alignment::Right,
+        None => Alignment::Left,
+    }
+}
+
+/// If the only content of `handle` (a `<sup>`) is a link, return its
+/// target, title and text.
+fn citation_link(handle: &Handle) -> Option<(String, Option<String>, String)> {
+    let mut link = None;
+    for child in handle.children.borrow().iter() {
+        match child.data {
+            markup5ever_rcdom::NodeData::Text { ref contents } if contents.borrow().trim().is_empty() => {}
+            Comment { .. } => {}
+            Element { ref name, .. } if link.is_none() && &name.local == "a" => link = Some(child.clone()),
+            _ => return None,
+        }
+    }
+    let link = link?;
+    let (href, title) = match link.data {
+        Element { ref attrs, .. } => {
+            let attrs = attrs.borrow();
+            let get = |key: &str| {
+                attrs
+                    .iter()
+                    .find(|attr| &attr.name.local == key)
+                    .map(|attr| attr.value.to_string())
+            };
+            (get("href")?, get("title"))
+        }
+        _ => return None,
+    };
+    let text = text_content(&link);
+    if text.is_empty() {
+        None
+    } else {
+        Some((href, title, text))
+    }
+}
+
+/// Whether `handle` is hidden with the `hidden` attribute or an inline
+/// `display: none` style.
+fn is_hidden(handle: &Handle) -> bool {
+    match handle.data {
+        Element { ref attrs, .. } => attrs.borrow().iter().any(|attr| {
+            &attr.name.local == "hidden"
+                || (&attr.name.local == "style"
+                    && parse_style(&attr.value)
+                        .iter()
+                        .any(|(name, value)| name == "display" && value.eq_ignore_ascii_case("none")))
+        }),
+        _ => false,
+    }
+}
+
+/// Whether `handle` is marked as right-to-left text with `dir="rtl"`.
+/// The text is right-aligned but otherwise left in logical order.
+fn is_rtl(handle: &Handle) -> bool {
+    match handle.data {
+        Element { ref attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .any(|attr| &attr.name.local == "dir" && attr.value.trim().eq_ignore_ascii_case("rtl")),
+        _ => false,
+    }
+}
+
+/// Split an inline `style` attribute into `(property, value)` pairs.
+/// Property names are lowercased; values are only trimmed.
+fn parse_style(style: &str) -> Vec<(String, String)> {
+    style
+        .split(';')
+        .filter_map(|decl| {
+            let mut parts = decl.splitn(2, ':');
+            let name = parts.next()?.trim().to_ascii_lowercase();
+            let value = parts.next()?.trim().to_string();
+            if name.is_empty() {
+                None
+            } else {
+                Some((name, value))
+            }
+        })
+        .collect()
+}
+
+/// The most blank lines a CSS margin or padding can add.
+const MAX_SPACING_LINES: usize = 10;
+
+/// Convert a CSS length to a number of text lines, very roughly
+/// (one line per `em`, 16px or 12pt), up to [`MAX_SPACING_LINES`].
+/// Returns `None` for lengths which can't be converted, such as
+/// percentages or `auto`.
+fn css_length_lines(value: &str) -> Option<usize> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let lines = match unit {
+        "em" | "rem" | "lh" => number,
+        "px" => number / 16.0,
+        "pt" => number / 12.0,
+        "" if number == 0.0 => 0.0,
+        _ => return None,
+    };
+    Some(lines.max(0.0).min(MAX_SPACING_LINES as f64).round() as usize)
+}
+
+/// Return the (top, bottom) blank lines set by a CSS `margin` or
+/// `padding` declaration, or `None` if it's not one of those properties.
+/// A declaration of just one side leaves the other as `None`.
+fn vertical_spacing_lines(name: &str, value: &str) -> Option<(Option<usize>, Option<usize>)> {
+    match name {
+        "margin-top" | "padding-top" => Some((Some(css_length_lines(value)?), None)),
+        "margin-bottom" | "padding-bottom" => Some((None, Some(css_length_lines(value)?))),
+        "margin" | "padding" => {
+            // The shorthand is "top [right [bottom [left]]]", with bottom
+            // defaulting to the same as top.
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            let top = css_length_lines(parts.first()?).unwrap_or(0);
+            let bottom = match parts.len() {
+                1 | 2 => top,
+                _ => css_length_lines(parts[2]).unwrap_or(0),
+            };
+            Some((Some(top), Some(bottom)))
+        }
+        _ => None,
+    }
+}
+
+/// Parse a CSS colour: `#rrggbb`, `#rgb` or one of the 16 basic named
+/// colours.  Returns `None` for anything else.
+fn parse_css_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let code = u32::from_str_radix(hex, 16).ok()?;
+        return match hex.len() {
+            6 => Some(Color::from_u32(code)),
+            // Each digit is repeated, so #f80 is #ff8800.
+            3 => Some(Color::new(
+                ((code >> 8) & 0xf) as u8 * 0x11,
+                ((code >> 4) & 0xf) as u8 * 0x11,
+                (code & 0xf) as u8 * 0x11,
+            )),
+            _ => None,
+        };
+    }
+    let code = match value.as_str() {
+        "black" => 0x000000,
+        "silver" => 0xc0c0c0,
+        "gray" | "grey" => 0x808080,
+        "white" => 0xffffff,
+        "maroon" => 0x800000,
+        "red" => 0xff0000,
+        "purple" => 0x800080,
+        "fuchsia" => 0xff00ff,
+        "green" => 0x008000,
+        "lime" => 0x00ff00,
+        "olive" => 0x808000,
+        "yellow" => 0xffff00,
+        "navy" => 0x000080,
+        "blue" => 0x0000ff,
+        "teal" => 0x008080,
+        "aqua" => 0x00ffff,
+        _ => return None,
+    };
+    Some(Color::from_u32(code))
+}
+
 /// Prepend a FragmentStart (or analogous) marker to an existing
 /// RenderNode.
 fn prepend_marker(prefix: RenderNode, mut orig: RenderNode) -> RenderNode {
@@ -918,9 +2497,15 @@ fn prepend_marker(prefix: RenderNode, mut orig: RenderNode) -> RenderNode {
         //
         // For Container, we do the same thing just to make the data
         // less pointlessly nested.
-        Block(ref mut children)
-        | Div(ref mut children)
-        | Pre(ref mut children)
+        Block(_, ref mut children)
+        | TaskItem(_, ref mut children)
+        | Figure(ref mut children)
+        | FigCaption(ref mut children)
+        | Caption(ref mut children)
+        | Attribution(ref mut children)
+        | Margin(_, _, ref mut children)
+        | Div(_, ref mut children)
+        | Pre(_, ref mut children)
         | BlockQuote(ref mut children)
         | Container(ref mut children)
         | TableCell(RenderTableCell {
@@ -965,15 +2550,18 @@ fn prepend_marker(prefix: RenderNode, mut orig: RenderNode) -> RenderNode {
 }
 
 fn process_dom_node<'a, 'b, T: Write>(
-    handle: Handle,
+    handle: &Handle,
+    base_url: Option<&Url>,
+    keep_hidden: bool,
     err_out: &'b mut T,
 ) -> TreeMapResult<'a, (), Handle, RenderNode> {
     use RenderNodeInfo::*;
     use TreeMapResult::*;
 
-    match handle.clone().data {
+    match handle.data {
         Document => pending(handle, |&mut (), cs| Some(RenderNode::new(Container(cs)))),
         Comment { .. } => Nothing,
+        Element { .. } if !keep_hidden && is_hidden(handle) => Nothing,
         Element {
             ref name,
             ref attrs,
@@ -989,35 +2577,48 @@ fn process_dom_node<'a, 'b, T: Write>(
                 }
                 expanded_name!(html "link")
                 | expanded_name!(html "meta")
-                | expanded_name!(html "hr")
                 | expanded_name!(html "script")
                 | expanded_name!(html "style")
                 | expanded_name!(html "head") => {
                     /* Ignore the head and its children */
                     Nothing
                 }
+                expanded_name!(html "sup") if citation_link(handle).is_some() => {
+                    let (href, title, text) = citation_link(handle).unwrap();
+                    Finished(RenderNode::new(Citation(resolve_url(base_url, &href), title, text)))
+                }
                 expanded_name!(html "a") => {
                     let borrowed = attrs.borrow();
                     let mut target = None;
+                    let mut title = None;
+                    let mut metadata = Vec::new();
                     frag_from_name_attr = true;
                     for attr in borrowed.iter() {
                         if &attr.name.local == "href" {
                             target = Some(&*attr.value);
-                            break;
+                        } else if &attr.name.local == "title" {
+                            title = Some(attr.value.to_string());
+                        } else if &attr.name.local == "rel" || &attr.name.local == "target" {
+                            metadata.push((attr.name.local.to_string(), attr.value.to_string()));
                         }
                     }
                     PendingChildren {
-                        children: handle.children.borrow().clone(),
+                        children: std::mem::take(&mut *handle.children.borrow_mut()),
                         cons: if let Some(href) = target {
                             // We need the closure to own the string it's going to use.
                             // Unfortunately that means we ideally want FnOnce; but
                             // that doesn't yet work in a Box.  Box<FnBox()> does, but
                             // is unstable.  So we'll just move a string in and clone
                             // it on use.
-                            let href: String = href.into();
+                            let href = resolve_url(base_url, href);
                             Box::new(move |_, cs: Vec<RenderNode>| {
                                 if cs.iter().any(|c| !c.is_shallow_empty()) {
-                                    Some(RenderNode::new(Link(href.clone(), cs)))
+                                    Some(RenderNode::new(Link(
+                                        href.clone(),
+                                        title.clone(),
+                                        metadata.clone(),
+                                        cs,
+                                    )))
                                 } else {
                                     None
                                 }
@@ -1036,9 +2637,41 @@ fn process_dom_node<'a, 'b, T: Write>(
                 expanded_name!(html "s") => {
                     pending(handle, |_, cs| Some(RenderNode::new(Strikeout(cs))))
                 }
+                expanded_name!(html "u") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(Underline(cs))))
+                }
+                expanded_name!(html "small") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(Small(cs))))
+                }
+                expanded_name!(html "mark") => pending(handle, |_, cs| {
+                    let mark = RenderNode::new(Mark(cs));
+                    Some(RenderNode::new(BgColored(vec![mark], Color::new(255, 255, 0))))
+                }),
+                expanded_name!(html "q") => {
+                    let cite = attrs
+                        .borrow()
+                        .iter()
+                        .find(|attr| &attr.name.local == "cite")
+                        .map(|attr| resolve_url(base_url, &attr.value));
+                    pending(handle, move |_, cs| {
+                        Some(RenderNode::new(Quote(cite.clone(), cs)))
+                    })
+                }
                 expanded_name!(html "code") => {
                     pending(handle, |_, cs| Some(RenderNode::new(Code(cs))))
                 }
+                expanded_name!(html "picture") => {
+                    // The <source>s are alternatives to the <img>, which
+                    // has the alt text.
+                    let img = handle.children.borrow().iter().find(|child| match child.data {
+                        Element { ref name, .. } => name.expanded() == expanded_name!(html "img"),
+                        _ => false,
+                    }).cloned();
+                    match img {
+                        Some(img) => process_dom_node(&img, base_url, keep_hidden, err_out),
+                        None => Nothing,
+                    }
+                }
                 expanded_name!(html "img") => {
                     let borrowed = attrs.borrow();
                     let mut width = None;
@@ -1074,12 +2707,9 @@ fn process_dom_node<'a, 'b, T: Write>(
                     }
                     let width = width.unwrap_or(0);
                     let height = height.unwrap_or(0);
-                    if title.is_none() {
-                        title = Some("No Alt Text Provided");
-                    }
-                    if let (Some(title), Some(src)) = (title, src) {
+                    if let Some(src) = src {
                         html_trace!("建立节点Img");
-                        Finished(RenderNode::new(Img(src.into(), title.into(),width,height)))
+                        Finished(RenderNode::new(Img(resolve_url(base_url, src), title.map(String::from),width,height)))
                     } else {
                         html_trace!("无内容Img");
                         Nothing
@@ -1095,46 +2725,94 @@ fn process_dom_node<'a, 'b, T: Write>(
                     })
                 }
                 expanded_name!(html "p") => {
-                    pending(handle, |_, cs| Some(RenderNode::new(Block(cs))))
+                    let alignment = block_alignment(handle);
+                    pending(handle, move |_, cs| Some(RenderNode::new(Block(alignment, cs))))
                 }
                 expanded_name!(html "div") => {
-                    pending(handle, |_, cs| Some(RenderNode::new(Div(cs))))
+                    let alignment = if is_rtl(handle) {
+                        Alignment::Right
+                    } else {
+                        Alignment::Left
+                    };
+                    pending(handle, move |_, cs| Some(RenderNode::new(Div(alignment, cs))))
+                }
+                expanded_name!(html "figure") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(Figure(cs))))
+                }
+                expanded_name!(html "figcaption") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(FigCaption(cs))))
+                }
+                expanded_name!(html "footer") | expanded_name!(html "cite")
+                    if parent_is_blockquote(handle) =>
+                {
+                    // Attributions are introduced with a dash, unless
+                    // the author has already written one.
+                    let text = text_content(handle);
+                    let has_dash = text.starts_with(|c| c == '—' || c == '–' || c == '-');
+                    pending(handle, move |_, mut cs| {
+                        if !has_dash {
+                            cs.insert(0, RenderNode::new(Text("— ".to_string())));
+                        }
+                        Some(RenderNode::new(Attribution(cs)))
+                    })
+                }
+                expanded_name!(html "caption") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(Caption(cs))))
                 }
                 expanded_name!(html "pre") => {
-                    pending(handle, |_, cs| Some(RenderNode::new(Pre(cs))))
+                    let language = pre_language(handle);
+                    pending(handle, move |_, cs| Some(RenderNode::new(Pre(language.clone(), cs))))
                 }
                 expanded_name!(html "br") => Finished(RenderNode::new(Break)),
-                expanded_name!(html "table") => table_to_render_tree(handle.clone(), err_out),
-                expanded_name!(html "thead") | expanded_name!(html "tbody") => {
-                    tbody_to_render_tree(handle.clone(), err_out)
+                expanded_name!(html "hr") => Finished(RenderNode::new(HorizontalRule)),
+                expanded_name!(html "wbr") => Finished(RenderNode::new(WordBreakOpportunity)),
+                expanded_name!(html "table") => table_to_render_tree(handle, err_out),
+                expanded_name!(html "thead")
+                | expanded_name!(html "tbody")
+                | expanded_name!(html "tfoot") => {
+                    tbody_to_render_tree(handle, err_out)
                 }
-                expanded_name!(html "tr") => tr_to_render_tree(handle.clone(), err_out),
+                expanded_name!(html "tr") => tr_to_render_tree(handle, err_out),
                 expanded_name!(html "th") | expanded_name!(html "td") => {
-                    td_to_render_tree(handle.clone(), err_out)
+                    td_to_render_tree(handle, err_out)
                 }
                 expanded_name!(html "blockquote") => {
                     pending(handle, |_, cs| Some(RenderNode::new(BlockQuote(cs))))
                 }
                 expanded_name!(html "ul") => Finished(RenderNode::new(Ul(
-                    list_children_to_render_nodes(handle.clone(), err_out),
+                    list_children_to_render_nodes(handle, base_url, keep_hidden, err_out)
+                        .into_iter()
+                        .map(|(_, item)| item)
+                        .collect(),
                 ))),
                 expanded_name!(html "ol") => {
                     let borrowed = attrs.borrow();
-                    let mut start = 1;
+                    let mut start = None;
+                    let mut style = OrderedListStyle::Decimal;
+                    let mut reversed = false;
                     for attr in borrowed.iter() {
                         if &attr.name.local == "start" {
-                            start = attr.value.parse().ok().unwrap_or(1);
-                            break;
+                            start = attr.value.parse().ok();
+                        }
+                        if &attr.name.local == "reversed" {
+                            reversed = true;
+                        }
+                        if &attr.name.local == "type" {
+                            style = OrderedListStyle::from_type_attr(&attr.value)
+                                .unwrap_or(OrderedListStyle::Decimal);
                         }
                     }
 
-                    Finished(RenderNode::new(Ol(
-                        start,
-                        list_children_to_render_nodes(handle.clone(), err_out),
-                    )))
+                    let (values, items): (Vec<_>, Vec<_>) =
+                        list_children_to_render_nodes(handle, base_url, keep_hidden, err_out)
+                            .into_iter()
+                            .unzip();
+                    // A reversed list counts down to 1 by default.
+                    let start = start.unwrap_or(if reversed { items.len() as i64 } else { 1 });
+                    Finished(RenderNode::new(Ol(start, style, reversed, values, items)))
                 }
                 expanded_name!(html "dl") => Finished(RenderNode::new(Dl(
-                    desc_list_children_to_render_nodes(handle.clone(), err_out),
+                    desc_list_children_to_render_nodes(handle, base_url, keep_hidden, err_out),
                 ))),
                 expanded_name!(html "color") => {
                     let borrowed = attrs.borrow();
@@ -1163,6 +2841,41 @@ fn process_dom_node<'a, 'b, T: Write>(
                         Nothing
                     }
                 }
+                expanded_name!(html "font") => {
+                    let color = attrs
+                        .borrow()
+                        .iter()
+                        .find(|attr| &attr.name.local == "color")
+                        .and_then(|attr| parse_css_color(&attr.value));
+                    match color {
+                        Some(c) => pending(handle, move |_, cs| Some(RenderNode::new(Colored(cs, c)))),
+                        None => pending(handle, |_, cs| Some(RenderNode::new(Container(cs)))),
+                    }
+                }
+                expanded_name!(html "input") => {
+                    let borrowed = attrs.borrow();
+                    let checked = borrowed.iter().any(|attr| &attr.name.local == "checked");
+                    let input_type = borrowed
+                        .iter()
+                        .find(|attr| &attr.name.local == "type")
+                        .map(|attr| attr.value.to_ascii_lowercase());
+                    match input_type {
+                        Some(kind) if kind == "checkbox" || kind == "radio" => {
+                            Finished(RenderNode::new(FormInput(kind, checked)))
+                        }
+                        _ => Nothing,
+                    }
+                }
+                expanded_name!(html "select") => {
+                    let multiple = attrs
+                        .borrow()
+                        .iter()
+                        .any(|attr| &attr.name.local == "multiple");
+                    Finished(RenderNode::new(Text(select_to_text(handle, multiple))))
+                }
+                expanded_name!(html "nobr") => {
+                    pending(handle, |_, cs| Some(RenderNode::new(NoBreak(cs))))
+                }
                 expanded_name!(html "section") => {
                     // let borrowed = attrs.borrow();
                     pending(handle, |_, cs| Some(RenderNode::new(Section(cs))))
@@ -1181,24 +2894,14 @@ fn process_dom_node<'a, 'b, T: Write>(
                     let uuid = uuid::Uuid::new_v4();
                     pending(handle, move |_, cs: Vec<RenderNode>| Some(RenderNode::new(Redacted(cs,pas.to_string(),uuid))))
                 }
-                expanded_name!(html "audio") => {
-                    let borrowed = attrs.borrow();
-                    let mut src = None;
-                    for attr in borrowed.iter() {
-                        if &attr.name.local == "src" && !attr.value.is_empty() {
-                            src = Some(&*attr.value);
-                        }
-                        if src.is_some() {
-                            break;
+                expanded_name!(html "audio") | expanded_name!(html "video") => {
+                    let audio = name.expanded() == expanded_name!(html "audio");
+                    match media_source(handle) {
+                        Some(src) => {
+                            let src = resolve_url(base_url, &src);
+                            Finished(RenderNode::new(if audio { Audio(src) } else { Video(src) }))
                         }
-                    }
-
-                    if let Some(src) = src {
-                        html_trace!("建立节点audio");
-                        Finished(RenderNode::new(Audio(src.to_string())))
-                    } else {
-                        html_trace!("无内容audio");
-                        Nothing
+                        None => Nothing,
                     }
                 }
                 // {
@@ -1223,6 +2926,79 @@ fn process_dom_node<'a, 'b, T: Write>(
                 }
             };
 
+            let mut underline = false;
+            let mut pre_wrap = false;
+            let mut nowrap = false;
+            let mut color = None;
+            let mut bgcolor = None;
+            // Later declarations override earlier ones, but the margin and
+            // padding are separate, so both count.
+            let (mut margin, mut padding) = ((0, 0), (0, 0));
+            for attr in attrs.borrow().iter() {
+                if &attr.name.local == "style" {
+                    for (name, value) in parse_style(&attr.value) {
+                        if (name == "text-decoration" || name == "text-decoration-line")
+                            && value
+                                .split_whitespace()
+                                .any(|v| v.eq_ignore_ascii_case("underline"))
+                        {
+                            underline = true;
+                        }
+                        if name == "white-space" && value.eq_ignore_ascii_case("pre-wrap") {
+                            pre_wrap = true;
+                        }
+                        if name == "white-space" && value.eq_ignore_ascii_case("nowrap") {
+                            nowrap = true;
+                        }
+                        if name == "color" {
+                            color = parse_css_color(&value);
+                        }
+                        if name == "background-color" {
+                            bgcolor = parse_css_color(&value);
+                        }
+                        if let Some((top, bottom)) = vertical_spacing_lines(&name, &value) {
+                            let spacing = if name.starts_with("margin") {
+                                &mut margin
+                            } else {
+                                &mut padding
+                            };
+                            spacing.0 = top.unwrap_or(spacing.0);
+                            spacing.1 = bottom.unwrap_or(spacing.1);
+                        }
+                    }
+                }
+            }
+            let result = if pre_wrap {
+                wrap_result(result, PreWrap)
+            } else {
+                result
+            };
+            let result = if nowrap {
+                wrap_result(result, NoBreak)
+            } else {
+                result
+            };
+            let result = if underline {
+                wrap_result(result, Underline)
+            } else {
+                result
+            };
+            let result = match color {
+                Some(c) => wrap_result(result, move |cs| Colored(cs, c)),
+                None => result,
+            };
+            let result = match bgcolor {
+                Some(c) => wrap_result(result, move |cs| BgColored(cs, c)),
+                None => result,
+            };
+            let margin_top = margin.0 + padding.0;
+            let margin_bottom = margin.1 + padding.1;
+            let result = if margin_top > 0 || margin_bottom > 0 {
+                wrap_result(result, move |cs| Margin(margin_top, margin_bottom, cs))
+            } else {
+                result
+            };
+
             let mut fragment = None;
             let borrowed = attrs.borrow();
             for attr in borrowed.iter() {
@@ -1274,18 +3050,70 @@ fn process_dom_node<'a, 'b, T: Write>(
     }
 }
 
+/// Something to render: a node of the tree, or part of a table or
+/// figure laid out while rendering it.  The tree itself is only
+/// borrowed, so that it can be rendered again.
+#[derive(Debug)]
+enum RenderItem<'a> {
+    /// A node of the tree.
+    Node(&'a RenderNode),
+    /// A figure caption, after a label such as `Figure 1: `.
+    LabelledCaption(String, &'a [RenderNode]),
+    /// A table's caption, rendered before its rows.
+    TableCaption(&'a [RenderNode]),
+    /// A table's rows, after its caption.
+    TableRows(Vec<RenderItem<'a>>),
+    /// A table row, with the width of each of its cells (or `None` to
+    /// leave the cell out), whether it's a header row, and whether the
+    /// cells are stacked vertically.
+    TableRow(&'a RenderTableRow, Vec<Option<usize>>, bool, bool),
+    /// A table cell, and the width it was given.
+    TableCell(&'a RenderTableCell, usize),
+}
+
 fn render_tree_to_string<T: Write, D: TextDecorator>(
     renderer: SubRenderer<D>,
-    tree: RenderNode,
+    tree: &RenderNode,
     err_out: &mut T,
 ) -> SubRenderer<D> {
     /* Phase 1: get size estimates. */
-    tree_map_reduce(&mut (), &tree, |_, node| precalc_size_estimate(&node));
+    let min_width = renderer.options().min_width.unwrap_or(MIN_WIDTH);
+    let mut budget = renderer.options().max_nodes;
+    precalc_size_estimates(tree, min_width, budget);
     /* Phase 2: actually render. */
+    let mut truncated = false;
     let mut renderer = TextRenderer::new(renderer);
-    tree_map_reduce(&mut renderer, tree, |renderer, node| {
-        do_render_node(renderer, node, err_out)
+    tree_map_reduce(&mut renderer, RenderItem::Node(tree), |renderer, item| {
+        if let Some(ref mut remaining) = budget {
+            // Leave out a table whose first row doesn't fit, rather than
+            // just drawing its top border.
+            let needed = match item {
+                RenderItem::Node(&RenderNode {
+                    info: RenderNodeInfo::Table(ref table),
+                    ..
+                }) => {
+                    // A caption adds itself and the rows' container.
+                    let caption = match table.caption.len() {
+                        0 => 0,
+                        n => n + 2,
+                    };
+                    2 + caption + table.rows().next().map_or(0, |row| row.cells().count())
+                }
+                _ => 1,
+            };
+            if *remaining < needed {
+                *remaining = 0;
+                truncated = true;
+                return skip_render_node(renderer, item, err_out);
+            }
+            *remaining -= 1;
+        }
+        do_render_node(renderer, item, err_out)
     });
+    if truncated {
+        // Don't leave the gap before a block which was cut off.
+        renderer.trim_trailing_blank_lines();
+    }
     let (mut renderer, links) = renderer.into_inner();
     let lines = renderer.finalise(links);
     // And add the links
@@ -1293,20 +3121,25 @@ fn render_tree_to_string<T: Write, D: TextDecorator>(
         renderer.start_block();
         renderer.fmt_links(lines);
     }
+    if let Some(max) = renderer.options().max_lines {
+        let ellipsis = renderer.options().ellipsis.clone().unwrap_or_else(|| "…".to_string());
+        renderer.truncate_lines(max, &ellipsis);
+    }
     renderer
 }
 
 fn pending2<
     'a,
+    'b,
     D: TextDecorator,
     F: Fn(&mut TextRenderer<D>, Vec<Option<SubRenderer<D>>>) -> Option<Option<SubRenderer<D>>>
         + 'static,
 >(
-    children: Vec<RenderNode>,
+    children: &'b [RenderNode],
     f: F,
-) -> TreeMapResult<'a, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
+) -> TreeMapResult<'a, TextRenderer<D>, RenderItem<'b>, Option<SubRenderer<D>>> {
     TreeMapResult::PendingChildren {
-        children,
+        children: children.iter().map(RenderItem::Node).collect(),
         cons: Box::new(f),
         prefn: None,
         postfn: None,
@@ -1315,21 +3148,78 @@ fn pending2<
 
 fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
     renderer: &mut TextRenderer<D>,
-    tree: RenderNode,
+    item: RenderItem<'a>,
     err_out: &'b mut T,
-) -> TreeMapResult<'static, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
-    html_trace!("do_render_node({:?}", tree);
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    html_trace!("do_render_node({:?}", item);
     use RenderNodeInfo::*;
     use TreeMapResult::*;
-    match tree.info {
-        Text(ref tstr) => {
+    let tree = match item {
+        RenderItem::Node(tree) => tree,
+        RenderItem::LabelledCaption(label, children) => {
+            renderer.start_block();
+            renderer.add_inline_text(&label);
+            return pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_block();
+                Some(None)
+            });
+        }
+        RenderItem::TableCaption(children) => {
+            renderer.start_block();
+            return pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_block();
+                Some(None)
+            });
+        }
+        RenderItem::TableRows(rows) => {
+            return PendingChildren {
+                children: rows,
+                cons: Box::new(|_, _| Some(None)),
+                prefn: None,
+                postfn: None,
+            };
+        }
+        RenderItem::TableRow(row, widths, header, vert) => {
+            return render_table_row_item(renderer, row, widths, header, vert, err_out);
+        }
+        RenderItem::TableCell(cell, _) => return render_table_cell(renderer, cell.header, &cell.content),
+    };
+    match &tree.info {
+        Text(tstr) => {
             renderer.add_inline_text(tstr);
             Finished(None)
         }
         Container(children) => pending2(children, |_, _| Some(None)),
-        Link(href, children) => {
-            renderer.start_link(&href);
-            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+        Citation(_, _, text) if renderer.options().ignore_links => {
+            renderer.add_inline_text(&text);
+            Finished(None)
+        }
+        Citation(href, title, text) => {
+            renderer.add_citation(href, title.as_deref(), text);
+            Finished(None)
+        }
+        FormInput(kind, checked) => {
+            let text = renderer.form_input(kind, *checked);
+            renderer.add_inline_text(&text);
+            Finished(None)
+        }
+        Link(_, _, _, children) if renderer.options().ignore_links => {
+            pending2(children, |_, _| Some(None))
+        }
+        Link(href, title, metadata, children) => {
+            renderer.start_link(href, title.as_deref());
+            let metadata = if renderer.options().link_metadata {
+                metadata.clone()
+            } else {
+                Vec::new()
+            };
+            for (name, value) in &metadata {
+                renderer.start_custom(name, vec![value.clone()]);
+            }
+            pending2(children, move |renderer: &mut TextRenderer<D>, _| {
+                for _ in &metadata {
+                    renderer.end_custom();
+                }
                 renderer.end_link();
                 Some(None)
             })
@@ -1355,9 +3245,57 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
                 Some(None)
             })
         }
-        Colored(children, color ) => {
-            renderer.start_color(color);
-            pending2(children, |renderer: &mut TextRenderer<D>,_| {
+        Margin(top, bottom, children) => {
+            let enabled = renderer.options().css_margins;
+            if enabled {
+                renderer.add_blank_lines(*top);
+            }
+            let bottom = *bottom;
+            pending2(children, move |renderer: &mut TextRenderer<D>, _| {
+                if enabled {
+                    renderer.add_blank_lines(bottom);
+                }
+                Some(None)
+            })
+        }
+        Underline(children) => {
+            renderer.start_underline();
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_underline();
+                Some(None)
+            })
+        }
+        Small(children) => {
+            renderer.start_small();
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_small();
+                Some(None)
+            })
+        }
+        Mark(children) => {
+            renderer.start_mark();
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_mark();
+                Some(None)
+            })
+        }
+        Quote(cite, children) => {
+            renderer.start_quote(cite.as_deref());
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_quote();
+                Some(None)
+            })
+        }
+        BgColored(children, color) => {
+            renderer.start_bgcolor(*color);
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_bgcolor();
+                Some(None)
+            })
+        }
+        Colored(children, color ) => {
+            renderer.start_color(*color);
+            pending2(children, |renderer: &mut TextRenderer<D>,_| {
                 renderer.end_color();
                 Some(None)
             })
@@ -1369,8 +3307,15 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
                 Some(None)
             })
         }
+        NoBreak(children) => {
+            renderer.start_nowrap();
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_nowrap();
+                Some(None)
+            })
+        }
         Redacted(children, psk, id)=> {
-            renderer.start_redacted(psk.clone(),id);
+            renderer.start_redacted(psk.clone(), *id);
             let cloned_id = Box::new(id.clone());
             let cloned_psk = Box::new(psk.clone());
             pending2(children, move |renderer: &mut TextRenderer<D>, _| {
@@ -1381,6 +3326,10 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             })
             // renderer.start_password();
         }
+        // A fenced block already marks its contents as code.
+        Code(children) if renderer.pre_depth() > 0 && renderer.options().pre_fence.is_some() => {
+            pending2(children, |_, _| Some(None))
+        }
         Code(children) => {
             renderer.start_code();
             pending2(children, |renderer: &mut TextRenderer<D>, _| {
@@ -1389,14 +3338,81 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             })
         }
         Img(src, title, w, h) => {
-            renderer.add_image(&src, &title,w ,h);
+            if renderer.options().image_links && (*w == 0 || *h == 0) {
+                // Show the image like a link to its source, so that the
+                // source is listed along with the links.
+                renderer.start_link(src, None);
+                renderer.add_inline_text(title.as_deref().unwrap_or("image"));
+                renderer.end_link();
+            } else {
+                renderer.add_image(src, title.as_deref(), *w, *h);
+            }
             Finished(None)
         }
         Audio(src) => {
-            renderer.add_asset("audio",vec![src]);
+            renderer.add_asset("audio", vec![src.clone()]);
             Finished(None)
         }
-        Block(children) => {
+        Video(src) => {
+            renderer.add_asset("video", vec![src.clone()]);
+            Finished(None)
+        }
+        Figure(children) => {
+            // Move any captions to the configured end of the figure.
+            let (captions, content): (Vec<_>, Vec<_>) = children
+                .iter()
+                .partition(|child| matches!(child.info, FigCaption(_)));
+            let mut captions = captions.into_iter().map(RenderItem::Node).peekable();
+            // The label goes inside the first caption's block.
+            let mut label = None;
+            if renderer.options().number_figures {
+                if let Some(RenderItem::Node(RenderNode { info: FigCaption(caption), .. })) = captions.peek() {
+                    let text = format!("Figure {}: ", renderer.next_figure_number());
+                    label = Some(RenderItem::LabelledCaption(text, caption));
+                    captions.next();
+                }
+            }
+            let captions = label.into_iter().chain(captions);
+            let content = content.into_iter().map(RenderItem::Node);
+            let children = match renderer.options().figure_caption {
+                CaptionPosition::Above => captions.chain(content).collect(),
+                CaptionPosition::Below => content.chain(captions).collect(),
+            };
+            renderer.new_line();
+            PendingChildren {
+                children,
+                cons: Box::new(|renderer: &mut TextRenderer<D>, _| {
+                    renderer.new_line();
+                    Some(None)
+                }),
+                prefn: None,
+                postfn: None,
+            }
+        }
+        Block(alignment, children) if *alignment != Alignment::Left => {
+            renderer.start_block();
+            renderer.start_element("p");
+            let outer = renderer.alignment();
+            renderer.set_alignment(*alignment);
+            pending2(children, move |renderer: &mut TextRenderer<D>, _| {
+                // Flush the lines while they're still aligned.
+                renderer.new_line();
+                renderer.set_alignment(outer);
+                renderer.end_element();
+                renderer.end_block();
+                Some(None)
+            })
+        }
+        Block(_, children) => {
+            renderer.start_block();
+            renderer.start_element("p");
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_element();
+                renderer.end_block();
+                Some(None)
+            })
+        }
+        TaskItem(_, children) | FigCaption(children) | Caption(children) => {
             renderer.start_block();
             pending2(children, |renderer: &mut TextRenderer<D>, _| {
                 renderer.end_block();
@@ -1404,40 +3420,73 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             })
         }
         Header(level, children) => {
-            let prefix = renderer.header_prefix(level);
-            let min_width = max(renderer.width(), 1 + prefix.len());
-            let sub_builder = renderer.new_sub_renderer(min_width - prefix.len());
+            let underline = match renderer.options().heading_style {
+                HeadingStyle::Setext if *level == 1 => Some('='),
+                HeadingStyle::Setext if *level == 2 => Some('-'),
+                _ => None,
+            };
+            let prefix = if underline.is_some() {
+                String::new()
+            } else {
+                renderer.header_prefix(*level)
+            };
+            let prefix_width = UnicodeWidthStr::width(prefix.as_str());
+            let min_width = max(renderer.width(), 1 + prefix_width);
+            let sub_builder = renderer.new_sub_renderer(min_width - prefix_width);
             renderer.push(sub_builder);
+            renderer.start_element(&format!("h{}", level));
             pending2(children, move |renderer: &mut TextRenderer<D>, _| {
-                let sub_builder = renderer.pop();
+                renderer.end_element();
+                let mut sub_builder = renderer.pop();
+                let width = sub_builder.content_width();
 
                 renderer.start_block();
                 renderer.append_subrender(sub_builder, repeat(&prefix[..]));
+                if let Some(c) = underline {
+                    renderer.add_inline_text(&c.to_string().repeat(width));
+                }
                 renderer.end_block();
                 Some(None)
             })
         }
-        Div(children) => {
+        Div(alignment, children) => {
             renderer.new_line();
-            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+            let outer = renderer.alignment();
+            if *alignment != Alignment::Left {
+                renderer.set_alignment(*alignment);
+            }
+            pending2(children, move |renderer: &mut TextRenderer<D>, _| {
                 renderer.new_line();
+                renderer.set_alignment(outer);
                 Some(None)
             })
         }
-        Pre(children) => {
+        Pre(language, children) => {
             renderer.new_line();
-            renderer.start_pre();
-            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+            renderer.start_pre(language.as_deref());
+            renderer.start_element("pre");
+            let numbered = renderer.options().pre_line_numbers && renderer.pre_depth() == 1;
+            if numbered {
+                renderer.start_line_numbers(count_pre_lines(children));
+            }
+            pending2(children, move |renderer: &mut TextRenderer<D>, _| {
                 renderer.new_line();
+                if numbered {
+                    renderer.end_line_numbers();
+                }
+                renderer.end_element();
                 renderer.end_pre();
                 Some(None)
             })
         }
         BlockQuote(children) => {
             let prefix = renderer.quote_prefix();
-            let sub_builder = renderer.new_sub_renderer(renderer.width() - prefix.len());
+            let prefix_width = UnicodeWidthStr::width(prefix.as_str());
+            let sub_builder = renderer.new_sub_renderer(renderer.width().saturating_sub(prefix_width));
             renderer.push(sub_builder);
+            renderer.start_element("blockquote");
             pending2(children, move |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_element();
                 let sub_builder = renderer.pop();
 
                 renderer.start_block();
@@ -1446,23 +3495,53 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
                 Some(None)
             })
         }
+        Attribution(children) => {
+            let sub_builder = renderer.new_sub_renderer(renderer.width());
+            renderer.push(sub_builder);
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                let mut sub_builder = renderer.pop();
+                // Right-align the attribution as a block.
+                let indent = " ".repeat(renderer.width().saturating_sub(sub_builder.content_width()));
+                renderer.start_block();
+                renderer.append_subrender(sub_builder, repeat(&indent[..]));
+                renderer.end_block();
+                Some(None)
+            })
+        }
         Ul(items) => {
             renderer.start_block();
 
-            let prefix = renderer.unordered_item_prefix();
-            let prefix_len = prefix.len();
+            let bullet = renderer.unordered_item_prefix();
+            // Task list items replace the bullet with their checkbox.
+            let prefixes: Vec<String> = items
+                .iter()
+                .map(|item| list_item_prefix(renderer, item, &bullet))
+                .collect();
+            let prefix_lens: Vec<usize> = prefixes
+                .iter()
+                .map(|prefix| UnicodeWidthStr::width(prefix.as_str()))
+                .collect();
+            let i: Cell<usize> = Cell::new(0);
+            let j: Cell<usize> = Cell::new(0);
 
             TreeMapResult::PendingChildren {
-                children: items,
+                children: items.iter().map(RenderItem::Node).collect(),
                 cons: Box::new(|_, _| Some(None)),
                 prefn: Some(Box::new(move |renderer: &mut TextRenderer<D>, _| {
-                    let sub_builder = renderer.new_sub_renderer(renderer.width() - prefix_len);
+                    let prefix_len = prefix_lens[j.get()];
+                    j.set(j.get() + 1);
+                    let mut sub_builder = renderer.new_sub_renderer(renderer.width().saturating_sub(prefix_len));
+                    sub_builder.enter_unordered_list();
                     renderer.push(sub_builder);
+                    renderer.start_element("li");
                 })),
                 postfn: Some(Box::new(move |renderer: &mut TextRenderer<D>, _| {
+                    renderer.end_element();
                     let sub_builder = renderer.pop();
 
-                    let indent = " ".repeat(prefix.len());
+                    let prefix = &prefixes[i.get()];
+                    i.set(i.get() + 1);
+                    let indent = " ".repeat(UnicodeWidthStr::width(prefix.as_str()));
 
                     renderer.append_subrender(
                         sub_builder,
@@ -1471,31 +3550,37 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
                 })),
             }
         }
-        Ol(start, items) => {
+        Ol(start, style, reversed, values, items) => {
             renderer.start_block();
 
-            let num_items = items.len();
-
-            // The prefix width could be at either end if the start is negative.
-            let min_number = start;
-            // Assumption: num_items can't overflow isize.
-            let max_number = start + (num_items as i64) - 1;
-            let prefix_width_min = renderer.ordered_item_prefix(min_number).len();
-            let prefix_width_max = renderer.ordered_item_prefix(max_number).len();
-            let prefix_width = max(prefix_width_min, prefix_width_max);
+            let numbers = ordered_list_numbers(*start, *reversed, values);
+            let style = *style;
+            // The widest prefix could be anywhere (Roman numerals vary a
+            // lot), so check all of them.
+            let prefix_width = numbers
+                .iter()
+                .map(|&i| UnicodeWidthStr::width(ordered_list_prefix(renderer, i, style).as_str()))
+                .max()
+                .unwrap_or(0);
             let prefixn = format!("{: <width$}", "", width = prefix_width);
-            let i: Cell<_> = Cell::new(start);
+            let i: Cell<usize> = Cell::new(0);
+            let markers: Vec<String> = numbers.iter().map(|&n| list_marker(n, style)).collect();
+            let j: Cell<usize> = Cell::new(0);
 
             TreeMapResult::PendingChildren {
-                children: items,
+                children: items.iter().map(RenderItem::Node).collect(),
                 cons: Box::new(|_, _| Some(None)),
                 prefn: Some(Box::new(move |renderer: &mut TextRenderer<D>, _| {
-                    let sub_builder = renderer.new_sub_renderer(renderer.width() - prefix_width);
+                    let mut sub_builder = renderer.new_sub_renderer(renderer.width().saturating_sub(prefix_width));
+                    sub_builder.enter_ordered_list_item(&markers[j.get()]);
+                    j.set(j.get() + 1);
                     renderer.push(sub_builder);
+                    renderer.start_element("li");
                 })),
                 postfn: Some(Box::new(move |renderer: &mut TextRenderer<D>, _| {
+                    renderer.end_element();
                     let sub_builder = renderer.pop();
-                    let prefix1 = renderer.ordered_item_prefix(i.get());
+                    let prefix1 = ordered_list_prefix(renderer, numbers[i.get()], style);
                     let prefix1 = format!("{: <width$}", prefix1, width = prefix_width);
 
                     renderer.append_subrender(
@@ -1510,7 +3595,7 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             renderer.start_block();
 
             TreeMapResult::PendingChildren {
-                children: items,
+                children: items.iter().map(RenderItem::Node).collect(),
                 cons: Box::new(|_, _| Some(None)),
                 prefn: None,
                 postfn: None,
@@ -1525,7 +3610,7 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             })
         }
         Dd(children) => {
-            let sub_builder = renderer.new_sub_renderer(renderer.width() - 2);
+            let sub_builder = renderer.new_sub_renderer(renderer.width().saturating_sub(2));
             renderer.push(sub_builder);
             pending2(children, |renderer: &mut TextRenderer<D>, _| {
                 let sub_builder = renderer.pop();
@@ -1534,37 +3619,276 @@ fn do_render_node<'a, 'b, T: Write, D: TextDecorator>(
             })
         }
         Break => {
-            renderer.new_line_hard();
+            match renderer.options().line_break.clone() {
+                Some(separator) => renderer.add_inline_text(&separator),
+                None => renderer.new_line_hard(),
+            }
             Finished(None)
         }
+        HorizontalRule => {
+            renderer.add_horizontal_rule();
+            Finished(None)
+        }
+        WordBreakOpportunity => {
+            renderer.add_break_opportunity();
+            Finished(None)
+        }
+        PreWrap(children) => {
+            renderer.start_pre_wrap();
+            pending2(children, |renderer: &mut TextRenderer<D>, _| {
+                renderer.end_pre_wrap();
+                Some(None)
+            })
+        }
         Table(tab) => render_table_tree(renderer, tab, err_out),
-        TableRow(row, false) => render_table_row(renderer, row, err_out),
-        TableRow(row, true) => render_table_row_vert(renderer, row, err_out),
+        TableRow(row, vert) => {
+            let widths = row.cell_widths(row.col_sizes.as_deref().unwrap(), row.column_gap, *vert);
+            render_table_row_item(renderer, row, widths, row.header, *vert, err_out)
+        }
         TableBody(_) => unimplemented!("Unexpected TableBody while rendering"),
-        TableCell(cell) => render_table_cell(renderer, cell, err_out),
+        TableCell(cell) => render_table_cell(renderer, cell.header, &cell.content),
         FragStart(fragname) => {
-            renderer.record_frag_start(&fragname);
+            renderer.record_frag_start(fragname);
             Finished(None)
         }
     }
 }
 
-fn render_table_tree<T: Write, D: TextDecorator>(
+/// Count the lines of preformatted text in `nodes`, not counting a final
+/// line break.
+fn count_pre_lines(nodes: &[RenderNode]) -> usize {
+    use RenderNodeInfo::*;
+    let mut lines = 1;
+    let mut last = None;
+    let mut stack: Vec<&RenderNode> = nodes.iter().rev().collect();
+    while let Some(node) = stack.pop() {
+        match node.info {
+            Text(ref text) => {
+                lines += text.matches('\n').count();
+                last = text.chars().last().or(last);
+            }
+            Break => {
+                lines += 1;
+                last = Some('\n');
+            }
+            Container(ref v) | Link(_, _, _, ref v) | Em(ref v) | Strong(ref v)
+            | Strikeout(ref v) | Underline(ref v) | PreWrap(ref v) | Small(ref v) | Mark(ref v) | Quote(_, ref v)
+            | Colored(ref v, _) | BgColored(ref v, _) | Code(ref v) | Pre(_, ref v) | Margin(_, _, ref v) => {
+                stack.extend(v.iter().rev());
+            }
+            _ => {}
+        }
+    }
+    if last == Some('\n') {
+        lines -= 1;
+    }
+    lines.max(1)
+}
+
+/// Skip over a node once the node budget has run out.  Table cells still
+/// have to produce a (now empty) sub-renderer to keep the stack balanced.
+fn skip_render_node<'a, T: Write, D: TextDecorator>(
     renderer: &mut TextRenderer<D>,
-    table: RenderTable,
+    item: RenderItem<'a>,
     _err_out: &mut T,
-) -> TreeMapResult<'static, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    match item {
+        RenderItem::TableCell(cell, _) => render_table_cell(renderer, cell.header, &[]),
+        RenderItem::Node(&RenderNode {
+            info: RenderNodeInfo::TableCell(ref cell),
+            ..
+        }) => render_table_cell(renderer, cell.header, &[]),
+        _ => TreeMapResult::Finished(None),
+    }
+}
+
+/// Return the prefix for an unordered list item: a checkbox for task
+/// list items, otherwise the usual bullet.
+fn list_item_prefix<D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    item: &RenderNode,
+    bullet: &str,
+) -> String {
+    match item.info {
+        RenderNodeInfo::TaskItem(checked, _) => renderer.task_item_prefix(checked),
+        _ => bullet.to_string(),
+    }
+}
+
+/// Return the number of each item in an ordered list.  An item with its
+/// own `value` takes that number, and the following items continue on
+/// from it.
+fn ordered_list_numbers(start: i64, reversed: bool, values: &[Option<i64>]) -> Vec<i64> {
+    let step = if reversed { -1 } else { 1 };
+    let mut next = start;
+    values
+        .iter()
+        .map(|value| {
+            let number = value.unwrap_or(next);
+            next = number.saturating_add(step);
+            number
+        })
+        .collect()
+}
+
+/// Return the prefix for the `index`th item of an ordered list.
+fn ordered_list_prefix<D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    index: i64,
+    style: OrderedListStyle,
+) -> String {
+    let prefix = match style {
+        OrderedListStyle::Decimal => renderer.ordered_item_prefix(index),
+        _ => renderer.ordered_item_marker_prefix(&list_marker(index, style)),
+    };
+    if renderer.options().hierarchical_ol {
+        format!("{}{}", renderer.ordered_list_context(), prefix)
+    } else {
+        prefix
+    }
+}
+
+/// Return the nodes directly inside `node`, not counting the contents of
+/// tables.
+fn child_nodes(node: &RenderNode) -> &[RenderNode] {
+    use RenderNodeInfo::*;
+    match node.info {
+        Container(ref v) | Link(_, _, _, ref v) | Em(ref v) | Strong(ref v)
+        | Strikeout(ref v) | Underline(ref v) | PreWrap(ref v) | Small(ref v) | Mark(ref v)
+        | Quote(_, ref v) | Colored(ref v, _) | BgColored(ref v, _) | Redacted(ref v, _, _)
+        | Code(ref v) | Block(_, ref v) | TaskItem(_, ref v) | Figure(ref v)
+        | FigCaption(ref v) | Caption(ref v) | Attribution(ref v) | Margin(_, _, ref v)
+        | Header(_, ref v) | Div(_, ref v) | Pre(_, ref v) | BlockQuote(ref v) | Ul(ref v)
+        | Ol(_, _, _, _, ref v) | Dl(ref v) | Dt(ref v) | Dd(ref v) | Section(ref v)
+        | NoBreak(ref v) => v,
+        _ => &[],
+    }
+}
+
+/// Return the width of the longest word in the text of `node`.
+fn longest_word_width(node: &RenderNode) -> usize {
+    match node.info {
+        RenderNodeInfo::Text(ref text) => text
+            .split_whitespace()
+            .map(UnicodeWidthStr::width)
+            .max()
+            .unwrap_or(0),
+        _ => child_nodes(node).iter().map(longest_word_width).max().unwrap_or(0),
+    }
+}
+
+/// Return how much wider the ordered lists in `node` are with
+/// hierarchical numbering than its size estimate allows for, which only
+/// counts each list's own markers, given `context` columns of numbering
+/// (e.g. `"1.2."`) from the lists it's already in.
+fn hierarchical_ol_width(node: &RenderNode, context: usize) -> usize {
+    let (context, nested) = match node.info {
+        RenderNodeInfo::Ol(start, style, reversed, ref values, _) => {
+            let marker_width = ordered_list_numbers(start, reversed, values)
+                .into_iter()
+                .map(|i| list_marker(i, style).len())
+                .max()
+                .unwrap_or(0);
+            // Every line of the list is indented by the context, and the
+            // lists nested in it have its marker added to theirs.
+            (context, context + marker_width + 1)
+        }
+        _ => (0, context),
+    };
+    context
+        + child_nodes(node)
+            .iter()
+            .map(|child| hierarchical_ol_width(child, nested))
+            .max()
+            .unwrap_or(0)
+}
+
+/// Return how much wider the tables in `node` are with `gap` spaces
+/// either side of their column separators than its size estimate, which
+/// leaves no gaps, allows for.
+fn column_gap_width(node: &RenderNode, gap: usize) -> usize {
+    match node.info {
+        RenderNodeInfo::Table(ref table) if gap > 0 && table.num_columns > 0 => {
+            let mut columns = vec![0; table.num_columns];
+            for row in table.rows() {
+                let mut colno = 0;
+                for cell in row.cells() {
+                    let extra = cell
+                        .content
+                        .iter()
+                        .map(|node| column_gap_width(node, gap))
+                        .max()
+                        .unwrap_or(0);
+                    let colspan = cell.colspan.min(table.num_columns - colno);
+                    for column in &mut columns[colno..colno + colspan] {
+                        *column = max(*column, (extra + colspan - 1) / colspan);
+                    }
+                    colno += colspan;
+                }
+            }
+            columns.iter().sum::<usize>() + (table.num_columns - 1) * 2 * gap
+        }
+        _ => child_nodes(node)
+            .iter()
+            .map(|child| column_gap_width(child, gap))
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+fn render_table_tree<'a, T: Write, D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    table: &'a RenderTable,
+    _err_out: &mut T,
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    let rows = table.ordered_rows(renderer.options().tfoot_last);
+    if renderer.pipe_tables() {
+        return render_pipe_table(renderer, table, rows);
+    }
+
     /* Now lay out the table. */
     let num_columns = table.num_columns;
+    let text_min_width = renderer.options().min_width.unwrap_or(MIN_WIDTH);
 
     /* Heuristic: scale the column widths according to how much content there is. */
     let mut col_sizes: Vec<SizeEstimate> = vec![Default::default(); num_columns];
 
+    // Cells spanning the whole table (banner rows) take whatever width the
+    // other rows need, so are only used for sizing if nothing else is.
+    let is_banner = |cell: &RenderTableCell| num_columns > 1 && cell.colspan == num_columns;
+    let only_banners = table
+        .rows()
+        .flat_map(RenderTableRow::cells)
+        .all(|cell| is_banner(cell) || cell.get_size_estimate(text_min_width).size == 0);
+
     for row in table.rows() {
         let mut colno = 0;
         for cell in row.cells() {
+            if is_banner(cell) && !only_banners {
+                continue;
+            }
             // FIXME: get_size_estimate is still recursive.
-            let mut estimate = cell.get_size_estimate();
+            let mut estimate = cell.get_size_estimate(text_min_width);
+            // The estimates leave out anything which depends on the
+            // render options.
+            let hierarchical_ol = renderer.options().hierarchical_ol;
+            let context = renderer.ordered_list_context().len();
+            let column_gap = renderer.options().column_gap;
+            let extra = cell
+                .content
+                .iter()
+                .map(|node| {
+                    let ol_extra = if hierarchical_ol {
+                        hierarchical_ol_width(node, context)
+                    } else {
+                        0
+                    };
+                    ol_extra + column_gap_width(node, column_gap)
+                })
+                .max()
+                .unwrap_or(0);
+            estimate.size += extra;
+            estimate.min_width += extra;
             // If the cell has a colspan>1, then spread its size between the
             // columns.
             estimate.size /= cell.colspan;
@@ -1576,58 +3900,67 @@ fn render_table_tree<T: Write, D: TextDecorator>(
         }
     }
     // TODO: remove empty columns
+    let column_gap = renderer.options().column_gap;
+    let separator = 1 + 2 * column_gap;
     let tot_size: usize = col_sizes.iter().map(|est| est.size).sum();
-    let min_size: usize = col_sizes.iter().map(|est| est.min_width).sum::<usize>()
-        + col_sizes.len().saturating_sub(1);
     let width = renderer.width();
 
-    let vert_row = min_size > width;
+    let mut col_widths: Vec<usize> = col_sizes
+        .iter()
+        .map(|sz| {
+            if sz.size == 0 {
+                0
+            } else {
+                min(
+                    sz.size,
+                    if usize::MAX / width <= sz.size {
+                        // The provided width is too large to multiply by width,
+                        // so do it the other way around.
+                        max((width / tot_size) * sz.size, sz.min_width)
+                    } else {
+                        max(sz.size * width / tot_size, sz.min_width)
+                    },
+                )
+            }
+        })
+        .collect();
+    apply_column_weights(
+        &mut col_widths,
+        &col_sizes,
+        &table.column_weights,
+        width.saturating_sub(num_columns.saturating_sub(1) * separator),
+    );
 
-    let mut col_widths: Vec<usize> = if !vert_row {
-        col_sizes
-            .iter()
-            .map(|sz| {
-                if sz.size == 0 {
-                    0
-                } else {
-                    min(
-                        sz.size,
-                        if usize::MAX / width <= sz.size {
-                            // The provided width is too large to multiply by width,
-                            // so do it the other way around.
-                            max((width / tot_size) * sz.size, sz.min_width)
-                        } else {
-                            max(sz.size * width / tot_size, sz.min_width)
-                        },
+    // Shrink the widest columns (compared to their minimum) until the
+    // table fits.  If it can't, the cells are stacked instead.
+    let mut vert_row = false;
+    let num_cols = col_widths.len();
+    if num_cols > 0 {
+        loop {
+            let cur_width = col_widths.iter().cloned().sum::<usize>() + (num_cols - 1) * separator;
+            if cur_width <= width {
+                break;
+            }
+            let shrinkable = col_widths
+                .iter()
+                .cloned()
+                .enumerate()
+                .filter(|&(colno, width)| width > col_sizes[colno].min_width)
+                .max_by_key(|&(colno, width)| {
+                    (
+                        width - col_sizes[colno].min_width,
+                        width,
+                        usize::max_value() - colno,
                     )
-                }
-            })
-            .collect()
-    } else {
-        col_sizes.iter().map(|_| width).collect()
-    };
-
-    if !vert_row {
-        let num_cols = col_widths.len();
-        if num_cols > 0 {
-            loop {
-                let cur_width = col_widths.iter().cloned().sum::<usize>() + num_cols - 1;
-                if cur_width <= width {
+                });
+            match shrinkable {
+                Some((i, _)) => col_widths[i] -= 1,
+                // Every column is at its minimum, and still too wide.
+                None => {
+                    vert_row = true;
+                    col_widths = vec![width; num_cols];
                     break;
                 }
-                let (i, _) = col_widths
-                    .iter()
-                    .cloned()
-                    .enumerate()
-                    .max_by_key(|&(colno, width)| {
-                        (
-                            width.saturating_sub(col_sizes[colno].min_width),
-                            width,
-                            usize::max_value() - colno,
-                        )
-                    })
-                    .unwrap();
-                col_widths[i] -= 1;
             }
         }
     }
@@ -1643,74 +3976,269 @@ fn render_table_tree<T: Write, D: TextDecorator>(
                 .filter(|&w| w > &0)
                 .count()
                 .saturating_sub(1)
+                * separator
     };
 
-    renderer.add_horizontal_border_width(table_width);
+    // A borderless table switches its renderer to BorderStyle::None until
+    // the table is finished; the cells' sub-renderers inherit it.  With
+    // single line output every table is borderless, so that only the
+    // words are left.  Cells
+    // must also fit their columns exactly, so can't use any width slack.
+    let border_style = renderer.options().border_style;
+    let width_slack = renderer.options().width_slack;
+    if table.borderless || renderer.options().single_line {
+        renderer.set_border_style(BorderStyle::None);
+    }
+    renderer.set_width_slack(0);
+
+    // A caption is centred over the table, so is rendered to the table's
+    // width (or that of its longest word) before the top border.
+    let caption = &table.caption;
+    let caption_word = caption.iter().map(longest_word_width).max().unwrap_or(0);
+    let caption_width = if vert_row || table_width == 0 {
+        width
+    } else {
+        max(table_width, min(caption_word, width))
+    };
+    let captioned = !caption.is_empty();
+    let rows: Vec<RenderItem> = rows
+        .into_iter()
+        .map(|row| {
+            let widths = row.cell_widths(&col_widths, column_gap, vert_row);
+            RenderItem::TableRow(row, widths, row.header, vert_row)
+        })
+        .collect();
+    let children = if !captioned {
+        renderer.add_horizontal_border_width(table_width);
+        rows
+    } else {
+        // Keep the caption with its table, so that it isn't split from it
+        // when paginating.
+        renderer.start_nobreak();
+        vec![RenderItem::TableCaption(caption), RenderItem::TableRows(rows)]
+    };
 
     TreeMapResult::PendingChildren {
-        children: table.into_rows(col_widths, vert_row),
-        cons: Box::new(|_, _| Some(None)),
-        prefn: Some(Box::new(|_, _| {})),
+        children,
+        cons: Box::new(move |renderer: &mut TextRenderer<D>, _| {
+            renderer.set_border_style(border_style);
+            renderer.set_width_slack(width_slack);
+            if captioned {
+                renderer.end_nobreak();
+            }
+            Some(None)
+        }),
+        prefn: Some(Box::new(move |renderer: &mut TextRenderer<D>, item| {
+            match item {
+                RenderItem::TableCaption(_) => {
+                    let mut sub_builder = renderer.new_sub_renderer(caption_width);
+                    sub_builder.set_alignment(Alignment::Center);
+                    renderer.push(sub_builder);
+                }
+                RenderItem::TableRows(_) => {
+                    let sub_builder = renderer.pop();
+                    renderer.append_subrender(sub_builder, repeat(""));
+                    renderer.add_horizontal_border_width(table_width);
+                }
+                _ => {}
+            }
+        })),
+        postfn: Some(Box::new(|_, _| {})),
+    }
+}
+
+/// Lay out a table as a pipe table.  Each cell gets the full width, as
+/// its lines are joined into one anyway, and the first row is taken as
+/// the header, since a pipe table must have one.
+fn render_pipe_table<'a, D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    table: &'a RenderTable,
+    rows: Vec<&'a RenderTableRow>,
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    let col_widths = vec![renderer.width(); table.num_columns];
+    // A caption goes in its own paragraph before the table.
+    let caption = &table.caption;
+    let captioned = !caption.is_empty();
+    let rows: Vec<RenderItem> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(rowno, row)| {
+            let widths = row.cell_widths(&col_widths, 0, false);
+            RenderItem::TableRow(row, widths, rowno == 0, false)
+        })
+        .collect();
+    renderer.start_block();
+    let children = if !captioned {
+        rows
+    } else {
+        renderer.start_nobreak();
+        vec![RenderItem::TableCaption(caption), RenderItem::TableRows(rows)]
+    };
+    TreeMapResult::PendingChildren {
+        children,
+        cons: Box::new(move |renderer: &mut TextRenderer<D>, _| {
+            if captioned {
+                renderer.end_nobreak();
+            }
+            Some(None)
+        }),
+        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, item| {
+            if let RenderItem::TableRows(_) = item {
+                renderer.start_block();
+            }
+        })),
         postfn: Some(Box::new(|_, _| {})),
     }
 }
 
-fn render_table_row<T: Write, D: TextDecorator>(
+/// Render a table row whose cells have the given `widths`, leaving out
+/// any without one.
+fn render_table_row_item<'a, T: Write, D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    row: &'a RenderTableRow,
+    widths: Vec<Option<usize>>,
+    header: bool,
+    vert: bool,
+    err_out: &mut T,
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    let cells = row
+        .cells()
+        .zip(widths)
+        .filter_map(|(cell, width)| Some(RenderItem::TableCell(cell, width?)))
+        .collect();
+    if vert {
+        render_table_row_vert(renderer, cells, err_out)
+    } else if renderer.pipe_tables() {
+        render_pipe_table_row(renderer, row, cells, header, err_out)
+    } else {
+        render_table_row(renderer, cells, header, err_out)
+    }
+}
+
+/// Push a sub-renderer for a table cell, as wide as its columns.
+fn push_cell_renderer<D: TextDecorator>(renderer: &mut TextRenderer<D>, item: &RenderItem) {
+    if let RenderItem::TableCell(_, width) = *item {
+        let sub_builder = renderer.new_sub_renderer(width);
+        renderer.push(sub_builder);
+    } else {
+        panic!()
+    }
+}
+
+/// Render a row of a pipe table; see `SubRenderer::append_pipe_row`.
+fn render_pipe_table_row<'a, T: Write, D: TextDecorator>(
     _renderer: &mut TextRenderer<D>,
-    row: RenderTableRow,
+    row: &'a RenderTableRow,
+    cells: Vec<RenderItem<'a>>,
+    header: bool,
     _err_out: &mut T,
-) -> TreeMapResult<'static, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    let colspans: Vec<usize> = row.cells().map(|cell| cell.colspan).collect();
     TreeMapResult::PendingChildren {
-        children: row.into_cells(false),
-        cons: Box::new(|builders, children| {
+        children: cells,
+        cons: Box::new(move |builders, children| {
+            let cells = children
+                .into_iter()
+                .map(Option::unwrap)
+                .zip(colspans.iter().cloned())
+                .collect();
+            builders.append_pipe_row(cells, header);
+            Some(None)
+        }),
+        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, item| push_cell_renderer(renderer, item))),
+        postfn: Some(Box::new(|_renderer: &mut TextRenderer<D>, _| {})),
+    }
+}
+
+/// Share out the width of the columns with a weight in `weights` in
+/// proportion to it, keeping the total within `available`.
+fn apply_column_weights(
+    col_widths: &mut [usize],
+    col_sizes: &[SizeEstimate],
+    weights: &[Option<usize>],
+    available: usize,
+) {
+    let weighted: Vec<(usize, usize)> = weights
+        .iter()
+        .take(col_widths.len())
+        .enumerate()
+        .filter_map(|(colno, weight)| weight.filter(|&w| w > 0).map(|w| (colno, w)))
+        .collect();
+    if weighted.is_empty() {
+        return;
+    }
+    let total_weight = weighted
+        .iter()
+        .fold(0usize, |total, &(_, w)| total.saturating_add(w));
+    let weighted_width: usize = weighted.iter().map(|&(colno, _)| col_widths[colno]).sum();
+    let other_width = col_widths.iter().sum::<usize>() - weighted_width;
+    let total = min(weighted_width, available.saturating_sub(other_width));
+    // Share out by cumulative weight, so that the rounding doesn't lose
+    // any of the width.
+    let mut cumulative: usize = 0;
+    let mut end = 0;
+    for (colno, weight) in weighted {
+        cumulative = cumulative.saturating_add(weight);
+        let next_end = (total as u128 * cumulative as u128 / total_weight as u128) as usize;
+        col_widths[colno] = max(next_end - end, col_sizes[colno].min_width);
+        end = next_end;
+    }
+}
+
+fn render_table_row<'a, T: Write, D: TextDecorator>(
+    _renderer: &mut TextRenderer<D>,
+    cells: Vec<RenderItem<'a>>,
+    header: bool,
+    _err_out: &mut T,
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    TreeMapResult::PendingChildren {
+        children: cells,
+        cons: Box::new(move |builders, children| {
             let children: Vec<_> = children.into_iter().map(Option::unwrap).collect();
             if children.iter().any(|c| !c.empty()) {
                 builders.append_columns_with_borders(children, true);
+                if header {
+                    builders.mark_header_border();
+                }
             }
             Some(None)
         }),
-        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, node| {
-            if let RenderNodeInfo::TableCell(ref cell) = node.info {
-                let sub_builder = renderer.new_sub_renderer(cell.col_width.unwrap());
-                renderer.push(sub_builder);
-            } else {
-                panic!()
-            }
-        })),
+        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, item| push_cell_renderer(renderer, item))),
         postfn: Some(Box::new(|_renderer: &mut TextRenderer<D>, _| {})),
     }
 }
 
-fn render_table_row_vert<T: Write, D: TextDecorator>(
+fn render_table_row_vert<'a, T: Write, D: TextDecorator>(
     _renderer: &mut TextRenderer<D>,
-    row: RenderTableRow,
+    cells: Vec<RenderItem<'a>>,
     _err_out: &mut T,
-) -> TreeMapResult<'static, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
     TreeMapResult::PendingChildren {
-        children: row.into_cells(true),
+        children: cells,
         cons: Box::new(|builders, children| {
             let children: Vec<_> = children.into_iter().map(Option::unwrap).collect();
             builders.append_vert_row(children);
             Some(None)
         }),
-        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, node| {
-            if let RenderNodeInfo::TableCell(ref cell) = node.info {
-                let sub_builder = renderer.new_sub_renderer(cell.col_width.unwrap());
-                renderer.push(sub_builder);
-            } else {
-                panic!()
-            }
-        })),
+        prefn: Some(Box::new(|renderer: &mut TextRenderer<D>, item| push_cell_renderer(renderer, item))),
         postfn: Some(Box::new(|_renderer: &mut TextRenderer<D>, _| {})),
     }
 }
 
-fn render_table_cell<T: Write, D: TextDecorator>(
-    _renderer: &mut TextRenderer<D>,
-    cell: RenderTableCell,
-    _err_out: &mut T,
-) -> TreeMapResult<'static, TextRenderer<D>, RenderNode, Option<SubRenderer<D>>> {
-    pending2(cell.content, |renderer: &mut TextRenderer<D>, _| {
+fn render_table_cell<'a, D: TextDecorator>(
+    renderer: &mut TextRenderer<D>,
+    header: bool,
+    content: &'a [RenderNode],
+) -> TreeMapResult<'static, TextRenderer<D>, RenderItem<'a>, Option<SubRenderer<D>>> {
+    renderer.start_element(if header { "th" } else { "td" });
+    if header {
+        renderer.start_table_header();
+    }
+    pending2(content, move |renderer: &mut TextRenderer<D>, _| {
+        if header {
+            renderer.end_table_header();
+        }
+        renderer.end_element();
         let sub_builder = renderer.pop();
         Some(Some(sub_builder))
     })
@@ -1724,19 +4252,66 @@ fn render_table_cell<T: Write, D: TextDecorator>(
 pub struct RenderTree(RenderNode);
 
 impl RenderTree {
+    /// Serialise the tree as JSON, e.g. to inspect how a document was
+    /// understood.  The cached size estimates aren't included.
+    ///
+    /// ```rust
+    /// let html = br#"<table><tr><td colspan="2"><a href="http://x/">x</a></td></tr></table>"#;
+    /// let json = html2text::parse(&html[..]).to_json();
+    /// assert!(json.contains(r#""colspan":2"#));
+    /// assert!(json.contains(r#""Link":["http://x/""#));
+    ///
+    /// let html = br#"<mask password="hunter2"><p>secret</p></mask>"#;
+    /// let json = html2text::parse(&html[..]).to_json();
+    /// assert!(json.contains("Redacted"));
+    /// assert!(!json.contains("hunter2"));
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).expect("render tree is always serialisable")
+    }
+
     /// Render this document using the given `decorator` and wrap it to `width` columns.
-    pub fn render<D: TextDecorator>(self, width: usize, decorator: D) -> RenderedText<D> {
-        let builder = SubRenderer::new(width, decorator);
-        let builder = render_tree_to_string(builder, self.0, &mut Discard {});
-        RenderedText(builder)
+    ///
+    /// The tree isn't consumed, so the same document can be rendered
+    /// again (e.g. at a new width after a terminal resize).
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::PlainDecorator;
+    ///
+    /// let tree = html2text::parse(&b"<p>one two three four</p>"[..]);
+    /// assert_eq!(tree.render(9, PlainDecorator::new()).into_string(), "one two\nthree\nfour\n");
+    /// assert_eq!(tree.render(14, PlainDecorator::new()).into_string(), "one two three\nfour\n");
+    /// assert_eq!(tree.render(80, PlainDecorator::new()).into_string(), "one two three four\n");
+    /// ```
+    pub fn render<D: TextDecorator>(&self, width: usize, decorator: D) -> RenderedText<D> {
+        self.render_with_options(width, decorator, RenderOptions::default())
     }
 
+    /// Render this document using the given `decorator` and layout `options`,
+    /// wrapping it to `width` columns.
+    pub fn render_with_options<D: TextDecorator>(
+        &self,
+        width: usize,
+        decorator: D,
+        options: RenderOptions,
+    ) -> RenderedText<D> {
+        // The size estimates don't depend on the width, so compute them
+        // on the shared tree where later renders can reuse them.
+        let min_width = options.min_width.unwrap_or(MIN_WIDTH);
+        precalc_size_estimates(&self.0, min_width, options.max_nodes);
+        // Too narrow a width leaves no room for any text.
+        let width = max(width, MIN_WIDTH);
+        let builder = SubRenderer::new_with_options(width, options, decorator);
+        let builder = render_tree_to_string(builder, &self.0, &mut Discard {});
+        RenderedText(builder)
+    }
 
     /// Render this document as rich text using the [`RichDecorator`][] and wrap it to `width`
     /// columns.
     ///
     /// [`RichDecorator`]: render/text_renderer/struct.RichDecorator.html
-    pub fn render_rich(self, width: usize) -> RenderedText<RichDecorator> {
+    pub fn render_rich(&self, width: usize) -> RenderedText<RichDecorator> {
         self.render(width, RichDecorator::new())
     }
 }
@@ -1753,16 +4328,137 @@ impl<D: TextDecorator> RenderedText<D> {
     /// Convert the rendered HTML document to a vector of lines with the annotations created by the
     /// decorator.
     pub fn into_lines(self) -> Vec<TaggedLine<Vec<D::Annotation>>> {
+        let trim_trailing = self.0.options().trim_trailing_whitespace;
         self.0
             .into_lines()
             .into_iter()
             .map(RenderLine::into_tagged_line)
+            .map(|mut line| {
+                if trim_trailing {
+                    line.trim_end();
+                }
+                line
+            })
             .collect()
     }
 }
 
+/// An error from reading or parsing HTML.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The document had nothing which could be rendered.
+    EmptyDocument,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "error reading HTML: {}", e),
+            Error::EmptyDocument => write!(f, "no content in HTML document"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::EmptyDocument => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
 /// Reads and parses HTML from `input` and prepares a render tree.
-pub fn parse(mut input: impl io::Read) -> RenderTree {
+///
+/// The tree can be kept and rendered several times (for example at
+/// different widths) without parsing the HTML again.
+///
+/// Panics if reading fails; see [`try_parse`] for a version which
+/// returns an error instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use html2text::parse;
+/// use html2text::render::text_renderer::PlainDecorator;
+///
+/// let tree = parse(&b"<p>The quick brown fox jumps over the lazy dog</p>"[..]);
+/// assert_eq!(
+///     tree.render(20, PlainDecorator::new()).into_string(),
+///     "The quick brown fox\njumps over the lazy\ndog\n"
+/// );
+/// assert_eq!(
+///     tree.render(40, PlainDecorator::new()).into_string(),
+///     "The quick brown fox jumps over the lazy\ndog\n"
+/// );
+/// ```
+pub fn parse<R: io::Read>(input: R) -> RenderTree {
+    try_parse(input).unwrap()
+}
+
+/// Reads and parses HTML from `input` and prepares a render tree,
+/// returning an error if the input can't be read.
+///
+/// Elements nested more than 256 deep are flattened, so that even
+/// pathologically deep documents can be rendered.
+///
+/// ```rust
+/// let depth = 1000;
+/// let html = format!("{}deep{}", "<div>".repeat(depth), "</div>".repeat(depth));
+/// let tree = html2text::try_parse(html.as_bytes()).unwrap();
+/// assert_eq!(tree.render_rich(80).into_string(), "deep\n");
+/// ```
+pub fn try_parse(input: impl io::Read) -> Result<RenderTree, Error> {
+    try_parse_with_base_url(input, None)
+}
+
+/// Like [`try_parse`], but resolving relative link targets and image
+/// sources against `base_url` if given.  A `<base href>` in the document
+/// takes precedence, itself resolved against `base_url`; without a
+/// `base_url` it is only used if it is an absolute URL.
+///
+/// ```rust
+/// use html2text::Url;
+///
+/// let base = Url::parse("http://example.com/x/y/z.html").unwrap();
+/// let convert = |html: &str| {
+///     html2text::config::plain().base_url(base.clone()).convert(html.as_bytes())
+/// };
+/// assert_eq!(convert(r#"<a href="/a/b">x</a>"#), "[x][1]\n\n[1]: http://example.com/a/b\n");
+/// assert_eq!(convert(r#"<a href="../c">x</a>"#), "[x][1]\n\n[1]: http://example.com/x/c\n");
+/// assert_eq!(
+///     convert(r#"<head><base href="http://other/d/"></head><a href="e">x</a>"#),
+///     "[x][1]\n\n[1]: http://other/d/e\n"
+/// );
+///
+/// let html = br#"<head><base href="http://b.com/q/"></head><a href="r">x</a>"#;
+/// assert_eq!(html2text::from_read(&html[..], 80), "[x][1]\n\n[1]: http://b.com/q/r\n");
+/// let html = br#"<head><base href="/q/"></head><a href="r">x</a>"#;
+/// assert_eq!(html2text::from_read(&html[..], 80), "[x][1]\n\n[1]: r\n");
+/// ```
+pub fn try_parse_with_base_url(
+    input: impl io::Read,
+    base_url: Option<&Url>,
+) -> Result<RenderTree, Error> {
+    try_parse_with(input, base_url, DEFAULT_MAX_DEPTH, false)
+}
+
+/// Like [`try_parse_with_base_url`], flattening elements nested more than
+/// `max_depth` deep, and keeping hidden elements if `keep_hidden` is set.
+fn try_parse_with(
+    mut input: impl io::Read,
+    base_url: Option<&Url>,
+    max_depth: usize,
+    keep_hidden: bool,
+) -> Result<RenderTree, Error> {
     let opts = ParseOpts {
         tree_builder: TreeBuilderOpts {
             drop_doctype: true,
@@ -1772,10 +4468,83 @@ pub fn parse(mut input: impl io::Read) -> RenderTree {
     };
     let dom = parse_document(RcDom::default(), opts)
         .from_utf8()
-        .read_from(&mut input)
-        .unwrap();
-    let render_tree = dom_to_render_tree(dom.document.clone(), &mut Discard {}).unwrap();
-    RenderTree(render_tree)
+        .read_from(&mut input)?;
+    let base_href = find_base_href(&dom.document);
+    let base_url = match base_url {
+        Some(base) => Some(
+            base_href
+                .and_then(|href| base.join(&href).ok())
+                .unwrap_or_else(|| base.clone()),
+        ),
+        None => base_href.and_then(|href| Url::parse(&href).ok()),
+    };
+    flatten_deep_nodes(&dom.document, max_depth);
+    let render_tree = dom_to_render_tree_with_base(
+        dom.document.clone(),
+        base_url.as_ref(),
+        keep_hidden,
+        &mut Discard {},
+    )
+    .ok_or(Error::EmptyDocument)?;
+    Ok(RenderTree(render_tree))
+}
+
+/// Reads HTML from `input`, and returns a `String` with text wrapped to
+/// `width` columns.  Widths narrower than a few columns are widened so
+/// that some text still fits.
+///
+/// ```rust
+/// let html = b"<blockquote>quote</blockquote><ul><li>a<ul><li>b</li></ul></li></ul>";
+/// for width in 0..3 {
+///     assert_eq!(
+///         html2text::from_read(&html[..], width),
+///         "> q\n> u\n> o\n> t\n> e\n\n* a\n  \n  * b\n"
+///     );
+/// }
+/// ```
+///
+/// Table columns are narrowed to fit, though not below their minimum
+/// width; if they still can't fit side by side, the cells are stacked
+/// instead.
+///
+/// ```rust
+/// let html = b"<table><tr><td>alpha</td><td>beta</td><td>gamma</td></tr></table>";
+/// assert_eq!(
+///     html2text::from_read(&html[..], 5),
+///     "─────\nalpha\n/////\nbeta\n/////\ngamma\n─────\n"
+/// );
+/// ```
+pub fn from_read<R>(input: R, width: usize) -> String
+where
+    R: io::Read,
+{
+    config::plain().width(width).convert(input)
+}
+
+/// Reads HTML from `input`, and returns a `String` with text wrapped to
+/// `width` columns, or an error if the input couldn't be read.
+///
+/// ```rust
+/// use std::io;
+///
+/// struct FailingReader;
+///
+/// impl io::Read for FailingReader {
+///     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+///         Err(io::Error::new(io::ErrorKind::Other, "broken pipe"))
+///     }
+/// }
+///
+/// match html2text::try_from_read(FailingReader, 80) {
+///     Err(html2text::Error::Io(e)) => assert_eq!(e.to_string(), "broken pipe"),
+///     other => panic!("expected an I/O error, got {:?}", other),
+/// }
+/// ```
+pub fn try_from_read<R>(input: R, width: usize) -> Result<String, Error>
+where
+    R: io::Read,
+{
+    config::plain().width(width).try_convert(input)
 }
 
 /// Reads HTML from `input`, decorates it using `decorator`, and
@@ -1785,7 +4554,9 @@ where
     R: io::Read,
     D: TextDecorator,
 {
-    parse(input).render(width, decorator).into_string()
+    config::Config::with_decorator(decorator)
+        .width(width)
+        .convert(input)
 }
 
 
@@ -1796,21 +4567,67 @@ pub fn from_read_rich<R>(input: R, width: usize) -> Vec<TaggedLine<Vec<RichAnnot
 where
     R: io::Read,
 {
-    parse(input)
-        .render(width, RichDecorator::new())
-        .into_lines()
+    config::rich().width(width).convert_lines(input)
+}
+
+/// Converts the HTML in `html` to a `String` with text wrapped to `width`
+/// columns.  This is the same as [`from_read`], without needing a reader.
+///
+/// ```rust
+/// let html = "<ul><li>Item one</li><li>Item two</li></ul>";
+/// assert_eq!(html2text::from_html(html, 20), "* Item one\n* Item two\n");
+/// ```
+pub fn from_html(html: &str, width: usize) -> String {
+    from_read(html.as_bytes(), width)
+}
+
+/// Converts the HTML in `html` to annotated lines wrapped to `width`
+/// columns.  This is the same as [`from_read_rich`], without needing a
+/// reader.
+///
+/// ```rust
+/// use html2text::render::text_renderer::RichAnnotation;
+///
+/// let lines = html2text::from_html_rich("<p>Some <em>emphasis</em></p>", 20);
+/// let tags: Vec<_> = lines[0].tagged_strings().map(|ts| (ts.s.as_str(), &ts.tag)).collect();
+/// assert_eq!(tags, vec![("Some ", &vec![]), ("emphasis", &vec![RichAnnotation::Emphasis])]);
+/// ```
+pub fn from_html_rich(html: &str, width: usize) -> Vec<TaggedLine<Vec<RichAnnotation>>> {
+    from_read_rich(html.as_bytes(), width)
+}
+
+/// Like [`from_read_rich`], but returns an error if the input couldn't be
+/// read instead of panicking.
+pub fn try_from_read_rich<R>(
+    input: R,
+    width: usize,
+) -> Result<Vec<TaggedLine<Vec<RichAnnotation>>>, Error>
+where
+    R: io::Read,
+{
+    config::rich().width(width).try_convert_lines(input)
 }
 
+#[cfg(feature = "encoding")]
+mod encoding;
+
+#[cfg(feature = "encoding")]
+pub use encoding::{decode_html, from_read_with_encoding, try_from_read_with_encoding};
+
 #[cfg(feature = "ansi_colours")]
 mod ansi_colours;
 
 
 #[cfg(feature = "ansi_colours")]
 pub use ansi_colours::custom_render;
+#[cfg(feature = "ansi_colours")]
+pub use ansi_colours::from_read_coloured;
 pub use ansi_colours::try_build_block;
 pub use ansi_colours::PageBlock;
 pub use ansi_colours::just_parse;
 pub use ansi_colours::just_render;
 #[cfg(feature = "ansi_colours")]
 pub use ansi_colours::Control;
+#[cfg(feature = "ansi_colours")]
+pub use ansi_colours::AnsiError;
 
@@ -36,14 +36,56 @@ pub trait Renderer {
     }
 
     /// Begin a preformatted block.  Until the corresponding end,
-    /// whitespace will used verbatim.  Pre regions can nest.
-    fn start_pre(&mut self);
+    /// whitespace will used verbatim.  Pre regions can nest.  `language`
+    /// is the language of the code in the block, if known.
+    fn start_pre(&mut self, language: Option<&str>);
 
     /// Finish a preformatted block started with `start_pre`.
     fn end_pre(&mut self);
 
     /// Add some inline text (which should be wrapped at the
     /// appropriate width) to the current block.
+    ///
+    /// Widths are counted in terminal columns, so a wide character such
+    /// as `漢` takes up two.  Soft hyphens (U+00AD) are places a word may
+    /// be broken, with a `-` shown at the break; they are otherwise left
+    /// out.  Other words too long for a line are broken between grapheme
+    /// clusters, so accents and emoji sequences are kept whole.
+    ///
+    /// ```rust
+    /// assert_eq!(html2text::from_html("<p>漢漢漢漢漢漢</p>", 6), "漢漢漢\n漢漢漢\n");
+    ///
+    /// let accented = "e\u{301}".repeat(5);
+    /// assert_eq!(
+    ///     html2text::from_html(&format!("<p>{}</p>", accented), 3),
+    ///     "e\u{301}e\u{301}e\u{301}\ne\u{301}e\u{301}\n"
+    /// );
+    /// let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    /// let text = html2text::from_html(&format!("<p>{}</p>", family.repeat(2)), 3);
+    /// assert!(text.lines().all(|line| line == family));
+    ///
+    /// let html = "<p>super&shy;cali&shy;fragilistic</p>";
+    /// assert_eq!(html2text::from_html(html, 12), "supercali-\nfragilistic\n");
+    /// assert_eq!(html2text::from_html(html, 30), "supercalifragilistic\n");
+    /// ```
+    ///
+    /// Text from separate calls joins up as in HTML: a word continues
+    /// across element boundaries, and a run of whitespace becomes one
+    /// space wherever it comes from.
+    ///
+    /// ```rust
+    /// use html2text::from_html;
+    ///
+    /// assert_eq!(from_html("<p>a<span> </span>b</p>", 80), "a b\n");
+    /// assert_eq!(from_html("<p>a <span> </span> b</p>", 80), "a b\n");
+    /// assert_eq!(from_html("<p>a<span>b</span>c<span></span>d</p>", 80), "abcd\n");
+    /// assert_eq!(from_html("<p><span>a </span> <span> b</span></p>", 80), "a b\n");
+    /// assert_eq!(from_html("<p>aaaa<span> </span>bbbb</p>", 5), "aaaa\nbbbb\n");
+    /// assert_eq!(from_html("<p>aaa<span>bb </span>cc</p>", 5), "aaabb\ncc\n");
+    ///
+    /// let lines = html2text::from_html_rich("<p><b>a</b><em>b</em> <b>c</b></p>", 80);
+    /// assert_eq!(lines[0].clone().into_string(), "ab c");
+    /// ```
     fn add_inline_text(&mut self, text: &str);
 
     /// Return the current width in character cells
@@ -88,7 +130,22 @@ pub trait Renderer {
     /// Finish a hyperlink started earlier.
     fn end_link(&mut self);
 
-    /// Start an emphasised region
+    /// Add a link whose text is a citation marker such as `[1]`, for
+    /// which the decorator may show just a footnote reference.
+    fn add_citation(&mut self, target: &str, text: &str);
+
+    /// Start an emphasised region.  Nested regions with the same
+    /// annotation only annotate the text once.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = format!("{}deep{}", "<em>".repeat(1000), "</em>".repeat(1000));
+    /// let lines = html2text::from_read_rich(html.as_bytes(), 80);
+    /// let strings: Vec<_> = lines[0].tagged_strings().collect();
+    /// assert_eq!(strings[0].s, "deep");
+    /// assert_eq!(strings[0].tag, vec![RichAnnotation::Emphasis]);
+    /// ```
     fn start_emphasis(&mut self);
 
     /// Finish emphasised text started earlier.
@@ -105,14 +162,73 @@ pub trait Renderer {
 
     /// Finish strikeout text started earlier.
     fn end_strikeout(&mut self);
+
+    /// Start an underlined region
+    fn start_underline(&mut self);
+
+    /// Finish underlined text started earlier.
+    fn end_underline(&mut self);
+
+    /// Start a de-emphasised (`<small>`) region.
+    fn start_small(&mut self);
+
+    /// Finish de-emphasised text started earlier.
+    fn end_small(&mut self);
+
+    /// Start a highlighted (`<mark>`) region.
+    fn start_mark(&mut self);
+
+    /// Finish highlighted text started earlier.
+    fn end_mark(&mut self);
+
+    /// Start the contents of a table header cell.
+    ///
+    /// With the plain decorator the rows of a `<thead>` are set off with
+    /// a double line, and with the rich decorator `<th>` cells are
+    /// annotated as strong.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::RichAnnotation;
+    ///
+    /// let html = b"<table><thead><tr><th>A</th><th>B</th></tr></thead>\
+    ///              <tbody><tr><td>1</td><td>2</td></tr></tbody></table>";
+    /// assert_eq!(html2text::from_read(&html[..], 20), "─┬─\nA│B\n═╪═\n1│2\n─┴─\n");
+    /// let lines = html2text::from_read_rich(&html[..], 20);
+    /// let headers: Vec<_> = lines[1].tagged_strings().filter(|ts| ts.s != "│").collect();
+    /// assert_eq!(headers.len(), 2);
+    /// assert!(headers.iter().all(|ts| ts.tag == vec![RichAnnotation::Strong]));
+    /// ```
+    fn start_table_header(&mut self);
+
+    /// Finish a table header cell started earlier.
+    fn end_table_header(&mut self);
+
+    /// Start an inline quotation, citing the `cite` URL if given.
+    fn start_quote(&mut self, cite: Option<&str>);
+
+    /// Finish a quotation started earlier.
+    fn end_quote(&mut self);
     /// Start a color region
     fn start_color(&mut self,color:crate::Color);
     /// End a colored region
     fn end_color(&mut self);
+    /// Start a region with a background colour
+    fn start_bgcolor(&mut self, color: crate::Color);
+    /// End a region with a background colour
+    fn end_bgcolor(&mut self);
     /// Start a nobreak
     fn start_nobreak(&mut self) ;
     /// End a nobreak
     fn end_nobreak(&mut self) ;
+    /// Start a region whose text isn't wrapped, e.g. `<nobr>`
+    fn start_nowrap(&mut self);
+    /// End a region whose text isn't wrapped
+    fn end_nowrap(&mut self);
+    /// Start a region whose text keeps its spaces and line breaks, like
+    /// CSS `white-space: pre-wrap`
+    fn start_pre_wrap(&mut self);
+    /// End a region whose text keeps its spaces and line breaks
+    fn end_pre_wrap(&mut self);
     /// Start an Annotated Region(when there's no decoration that adds strings)
     #[allow(unused_variables)]
     fn start_redacted(&mut self, psk:String, id: uuid::Uuid){
@@ -127,8 +243,8 @@ pub trait Renderer {
     /// End a code region
     fn end_code(&mut self);
 
-    /// Add an image
-    fn add_image(&mut self, src: &str, title: &str, w:usize, h:usize);
+    /// Add an image, with its alt text if it has any.
+    fn add_image(&mut self, src: &str, alt: Option<&str>, w:usize, h:usize);
     /// 
     fn add_asset(&mut self, key: &str, value: Self::Asset);
     /// Get prefix string of header in specific level.
@@ -143,6 +259,27 @@ pub trait Renderer {
     /// Get prefix string of ith ordered list item.
     fn ordered_item_prefix(&mut self, i: i64) -> String;
 
+    /// Get prefix string of an ordered list item numbered with `marker`,
+    /// such as a letter or Roman numeral.
+    fn ordered_item_marker_prefix(&mut self, marker: &str) -> String;
+
+    /// Get prefix string of a task list item.
+    fn task_item_prefix(&mut self, checked: bool) -> String;
+
+    /// Get the text for a checkbox or radio button (`kind`) form input.
+    fn form_input(&mut self, kind: &str, checked: bool) -> String;
+
     /// Record the start of a named HTML fragment
     fn record_frag_start(&mut self, fragname: &str);
+
+    /// Start the contents of an HTML element named `name`, such as `"p"`
+    /// or `"h1"`.
+    fn start_element(&mut self, name: &str);
+
+    /// Finish the element most recently started.
+    fn end_element(&mut self);
+
+    /// Allow the text to be wrapped at this point, without adding a
+    /// hyphen or space.
+    fn add_break_opportunity(&mut self);
 }
@@ -4,11 +4,16 @@
 //! into different text formats.
 
 use super::Renderer;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::rc::Rc;
 use std::vec;
 use std::{collections::LinkedList, fmt::Debug};
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Context to use during tree parsing.
@@ -17,7 +22,8 @@ use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 #[derive(Clone, Debug)]
 pub struct TextRenderer<D: TextDecorator> {
     subrender: Vec<SubRenderer<D>>,
-    links: Vec<String>,
+    links: Vec<LinkRef>,
+    figures: usize,
 }
 
 impl<D: TextDecorator> Deref for TextRenderer<D> {
@@ -40,17 +46,41 @@ impl<D: TextDecorator> TextRenderer<D> {
         TextRenderer {
             subrender: vec![subrenderer],
             links: Vec::new(),
+            figures: 0,
         }
     }
 
+    /// Count another numbered figure in the document, returning its number.
+    pub fn next_figure_number(&mut self) -> usize {
+        self.figures += 1;
+        self.figures
+    }
+
     // hack overloads start_link method otherwise coming from the Renderer trait
     // impl on SubRenderer
     /// Add link to global link collection
-    pub fn start_link(&mut self, target: &str) {
-        self.links.push(target.to_string());
+    pub fn start_link(&mut self, target: &str, title: Option<&str>) {
+        if self.options().link_style == LinkStyle::Footnote {
+            self.links.push(LinkRef {
+                url: target.to_string(),
+                title: title.map(str::to_string),
+            });
+        }
         self.subrender.last_mut().unwrap().start_link(target);
     }
 
+    /// Add a citation link with text `text`, recording the link like
+    /// [`start_link`](TextRenderer::start_link).
+    pub fn add_citation(&mut self, target: &str, title: Option<&str>, text: &str) {
+        if self.options().link_style == LinkStyle::Footnote {
+            self.links.push(LinkRef {
+                url: target.to_string(),
+                title: title.map(str::to_string),
+            });
+        }
+        self.subrender.last_mut().unwrap().add_citation(target, text);
+    }
+
     /// Push a new builder onto the stack
     pub fn push(&mut self, builder: SubRenderer<D>) {
         self.subrender.push(builder);
@@ -64,12 +94,27 @@ impl<D: TextDecorator> TextRenderer<D> {
 
     /// Pop off the only builder and return it.
     /// panics if there aren't exactly 1 available.
-    pub fn into_inner(mut self) -> (SubRenderer<D>, Vec<String>) {
+    pub fn into_inner(mut self) -> (SubRenderer<D>, Vec<LinkRef>) {
         assert_eq!(self.subrender.len(), 1);
         (self.subrender.pop().unwrap(), self.links)
     }
 }
 
+/// A link found while rendering, passed to `TextDecorator::finalise` so
+/// that it can be listed after the text.
+///
+/// ```rust
+/// let html = br#"<a href="u" title="t">x</a>"#;
+/// assert_eq!(html2text::from_read(&html[..], 20), "[x][1]\n\n[1]: u \"t\"\n");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkRef {
+    /// The link target.
+    pub url: String,
+    /// The link's `title` attribute, if it had one.
+    pub title: Option<String>,
+}
+
 /// A wrapper around a String with extra metadata.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TaggedString<T> {
@@ -252,6 +297,25 @@ impl<T: Debug + Eq + PartialEq + Clone + Default> TaggedLine<T> {
         self.tagged_strings().map(TaggedString::width).sum()
     }
 
+    /// Remove any trailing whitespace from the end of the line, dropping
+    /// strings which are left empty.
+    pub fn trim_end(&mut self) {
+        use self::TaggedLineElement::Str;
+
+        let mut i = self.v.len();
+        while i > 0 {
+            i -= 1;
+            if let Str(ref mut ts) = self.v[i] {
+                let len = ts.s.trim_end().len();
+                ts.s.truncate(len);
+                if len > 0 {
+                    break;
+                }
+                self.v.remove(i);
+            }
+        }
+    }
+
     /// Pad this line to width with spaces (or if already at least this wide, do
     /// nothing).
     pub fn pad_to(&mut self, width: usize) {
@@ -267,53 +331,170 @@ impl<T: Debug + Eq + PartialEq + Clone + Default> TaggedLine<T> {
     }
 }
 
+/// The state of the line numbers for a preformatted block.
+#[derive(Clone, Copy, Debug)]
+struct LineNumbers {
+    /// The number of the next line.
+    next: usize,
+    /// The width of the widest line number.
+    width: usize,
+}
+
 /// A type to build up wrapped text, allowing extra metadata for
 /// spans.
 #[derive(Debug, Clone)]
 struct WrappedBlock<T> {
     width: usize,
+    slack: usize, // How far a word may overrun the width.
     text: Vec<TaggedLine<T>>,
     textlen: usize,
     line: TaggedLine<T>,
     linelen: usize,
     spacetag: Option<T>, // Tag for the whitespace before the current word
+    spaces: usize, // Preserved spaces before the current word, if any.
+    keep_leading_spaces: bool, // If true, preserved spaces may start the line.
     word: TaggedLine<T>, // The current word (with no whitespace).
     wordlen: usize,
+    word_shy: Vec<usize>, // Offsets of soft hyphens in the current word.
+    word_wbr: Vec<usize>, // Offsets of other break opportunities in the word.
+    word_nowrap: bool, // If true, the word overflows rather than being split.
     pre_wrapped: bool, // If true, we've been forced to wrap a <pre> line.
 }
 
 impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
-    pub fn new(width: usize) -> WrappedBlock<T> {
+    pub fn new(width: usize, slack: usize) -> WrappedBlock<T> {
         WrappedBlock {
             width,
+            slack,
             text: Vec::new(),
             textlen: 0,
             line: TaggedLine::new(),
             linelen: 0,
             spacetag: None,
+            spaces: 0,
+            keep_leading_spaces: true,
             word: TaggedLine::new(),
             wordlen: 0,
+            word_shy: Vec::new(),
+            word_wbr: Vec::new(),
+            word_nowrap: false,
             pre_wrapped: false,
         }
     }
 
+    /// Split the current word at its last break opportunity which fits
+    /// within `space` cells (leaving room for a `-` at a soft hyphen),
+    /// returning the part before it, and whether it needs a hyphen, and
+    /// leaving the rest in `self.word`.
+    fn split_word_at_break(&mut self, space: usize) -> Option<(TaggedLine<T>, bool)> {
+        use self::TaggedLineElement::Str;
+
+        let shy = self
+            .word_shy
+            .iter()
+            .rev()
+            .find(|&&pos| pos > 0 && pos < space)
+            .map(|&pos| (pos, true));
+        let wbr = self
+            .word_wbr
+            .iter()
+            .rev()
+            .find(|&&pos| pos > 0 && pos <= space)
+            .map(|&pos| (pos, false));
+        let (pos, hyphen) = match (shy, wbr) {
+            (Some(shy), Some(wbr)) if shy.0 > wbr.0 => shy,
+            (_, Some(wbr)) => wbr,
+            (shy, None) => shy?,
+        };
+        let mut head = TaggedLine::new();
+        let mut rest = TaggedLine::new();
+        let mut left = pos;
+        for elt in self.word.drain_all() {
+            match elt {
+                Str(piece) if left > 0 => {
+                    let w = piece.width();
+                    if w <= left {
+                        left -= w;
+                        head.push(Str(piece));
+                    } else {
+                        let mut split_idx = piece.s.len();
+                        let mut piece_w = 0;
+                        for (idx, c) in piece.s.char_indices() {
+                            if piece_w >= left {
+                                split_idx = idx;
+                                break;
+                            }
+                            piece_w += UnicodeWidthChar::width(c).unwrap_or(0);
+                        }
+                        head.push(Str(TaggedString {
+                            s: piece.s[..split_idx].into(),
+                            tag: piece.tag.clone(),
+                        }));
+                        rest.push(Str(TaggedString {
+                            s: piece.s[split_idx..].into(),
+                            tag: piece.tag,
+                        }));
+                        left = 0;
+                    }
+                }
+                elt => {
+                    if left > 0 {
+                        head.push(elt);
+                    } else {
+                        rest.push(elt);
+                    }
+                }
+            }
+        }
+        self.word = rest;
+        self.wordlen -= pos;
+        self.word_shy = self
+            .word_shy
+            .iter()
+            .filter(|&&p| p > pos)
+            .map(|&p| p - pos)
+            .collect();
+        self.word_wbr = self
+            .word_wbr
+            .iter()
+            .filter(|&&p| p > pos)
+            .map(|&p| p - pos)
+            .collect();
+        Some((head, hyphen))
+    }
+
     fn flush_word(&mut self) {
         use self::TaggedLineElement::Str;
 
         /* Finish the word. */
         html_trace_quiet!("flush_word: word={:?}, linelen={}", self.word, self.linelen);
-        if !self.word.is_empty() {
+        if !self.word.is_empty() && self.wordlen == 0 {
+            /* Only zero-width markers (e.g. fragment starts); attach them to
+             * the line without using up the pending space, otherwise the
+             * space is emitted twice across the inline boundary. */
+            self.line.consume(&mut self.word);
+        } else if !self.word.is_empty() {
             self.pre_wrapped = false;
-            let space_in_line = self.width - self.linelen;
-            let space_needed = self.wordlen + if self.linelen > 0 { 1 } else { 0 }; // space
-            if space_needed <= space_in_line {
+            let spaces = if self.linelen > 0 {
+                self.spaces.max(1)
+            } else if self.keep_leading_spaces {
+                self.spaces
+            } else {
+                0
+            };
+            self.spaces = 0;
+            let space_in_line = self.width.saturating_sub(self.linelen);
+            let space_needed = self.wordlen + spaces;
+            if space_needed <= space_in_line
+                || self.linelen + space_needed <= self.width + self.slack
+            {
                 html_trace!("Got enough space");
-                if self.linelen > 0 {
+                if spaces > 0 {
                     self.line.push(Str(TaggedString {
-                        s: " ".into(),
+                        s: " ".repeat(spaces),
                         tag: self.spacetag.take().unwrap_or_else(|| Default::default()),
                     }));
-                    self.linelen += 1;
+                    self.linelen += spaces;
                     html_trace!("linelen incremented to {}", self.linelen);
                 }
                 self.line.consume(&mut self.word);
@@ -321,9 +502,38 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
                 html_trace!("linelen increased by wordlen to {}", self.linelen);
             } else {
                 html_trace!("Not enough space");
+                /* Break at a soft hyphen or <wbr> if we can, putting the
+                 * start of the word (and a hyphen) on this line. */
+                let space_for_word = space_in_line.saturating_sub(space_needed - self.wordlen);
+                if let Some((mut head, hyphen)) = self.split_word_at_break(space_for_word) {
+                    let tag = head
+                        .tagged_strings()
+                        .last()
+                        .map(|ts| ts.tag.clone())
+                        .unwrap_or_default();
+                    if spaces > 0 {
+                        self.line.push(Str(TaggedString {
+                            s: " ".repeat(spaces),
+                            tag: self.spacetag.take().unwrap_or_default(),
+                        }));
+                    }
+                    self.line.consume(&mut head);
+                    if hyphen {
+                        self.line.push(Str(TaggedString { s: "-".into(), tag }));
+                    }
+                    self.force_flush_line();
+                    return self.flush_word();
+                }
                 /* Start a new line */
                 self.flush_line();
-                if self.wordlen <= self.width {
+                if self.wordlen > self.width
+                    && (self.word_shy.iter().any(|&pos| pos > 0 && pos < self.width)
+                        || self.word_wbr.iter().any(|&pos| pos > 0 && pos <= self.width))
+                {
+                    /* Now it can be broken at a soft hyphen or <wbr>. */
+                    return self.flush_word();
+                }
+                if self.wordlen <= self.width || self.word_nowrap {
                     html_trace!("wordlen <= width");
                     let mut new_word = TaggedLine::new();
                     mem::swap(&mut new_word, &mut self.word);
@@ -348,12 +558,15 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
                                 html_trace!("linelen had w={} added to {}", w, self.linelen);
                                 opt_elt = wordbits.next();
                             } else {
-                                /* Split into two */
-                                let mut split_idx = 0;
-                                for (idx, c) in piece.s.char_indices() {
-                                    let c_w = UnicodeWidthChar::width(c).unwrap();
-                                    if c_w <= lineleft {
-                                        lineleft -= c_w;
+                                /* Split into two, between grapheme clusters so
+                                 * that e.g. combining accents stay with their
+                                 * base character.  A cluster wider than the
+                                 * whole line is put on a line by itself. */
+                                let mut split_idx = piece.s.len();
+                                for (idx, g) in piece.s.grapheme_indices(true) {
+                                    let g_w = UnicodeWidthStr::width(g);
+                                    if g_w <= lineleft || lineleft == self.width {
+                                        lineleft = lineleft.saturating_sub(g_w);
                                     } else {
                                         split_idx = idx;
                                         break;
@@ -371,10 +584,14 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
                                 lineleft = self.width;
                                 self.linelen = 0;
                                 html_trace!("linelen set to zero here");
-                                opt_elt = Some(Str(TaggedString {
-                                    s: piece.s[split_idx..].into(),
-                                    tag: piece.tag,
-                                }));
+                                opt_elt = if split_idx < piece.s.len() {
+                                    Some(Str(TaggedString {
+                                        s: piece.s[split_idx..].into(),
+                                        tag: piece.tag,
+                                    }))
+                                } else {
+                                    wordbits.next()
+                                };
                             }
                         } else {
                             self.line.push(elt);
@@ -385,6 +602,9 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
             }
         }
         self.wordlen = 0;
+        self.word_shy.clear();
+        self.word_wbr.clear();
+        self.word_nowrap = false;
     }
 
     fn flush_line(&mut self) {
@@ -398,6 +618,7 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
         mem::swap(&mut tmp_line, &mut self.line);
         self.text.push(tmp_line);
         self.linelen = 0;
+        self.keep_leading_spaces = false;
     }
 
     fn flush(&mut self) {
@@ -430,12 +651,69 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
     }
 
     pub fn add_text(&mut self, text: &str, tag: &T) {
+        self.add_text_inner(text, tag, false);
+    }
+
+    /// Add text which mustn't be wrapped at its spaces.  A run of
+    /// whitespace becomes a single space within the current word.
+    pub fn add_text_nowrap(&mut self, text: &str, tag: &T) {
+        self.add_text_inner(text, tag, true);
+    }
+
+    /// Add text whose spaces and line breaks are kept, like CSS
+    /// `white-space: pre-wrap`.  It is still wrapped between words, and
+    /// spaces where a line is wrapped are dropped.
+    pub fn add_text_pre_wrap(&mut self, text: &str, tab_stop: usize, tag: &T) {
+        let mut rest = text;
+        while let Some(pos) = rest.find(|c| c == ' ' || c == '\t' || c == '\n') {
+            self.add_text_inner(&rest[..pos], tag, false);
+            self.flush_word();
+            match rest.as_bytes()[pos] {
+                b'\n' => {
+                    self.force_flush_line();
+                    self.spaces = 0;
+                    self.keep_leading_spaces = true;
+                }
+                b'\t' => {
+                    let column = self.linelen + self.spaces;
+                    self.spaces += tab_stop - column % tab_stop;
+                }
+                _ => self.spaces += 1,
+            }
+            self.spacetag = Some(tag.clone());
+            rest = &rest[pos + 1..];
+        }
+        self.add_text_inner(rest, tag, false);
+    }
+
+    fn add_text_inner(&mut self, text: &str, tag: &T, nowrap: bool) {
+        use self::TaggedLineElement::Str;
+
         html_trace!("WrappedBlock::add_text({}), {:?}", text, tag);
         for c in text.chars() {
-            if c.is_whitespace() {
+            if nowrap && !c.is_whitespace() {
+                self.word_nowrap = true;
+            }
+            if nowrap && self.wordlen > 0 && c.is_whitespace() {
+                let after_space = match self.word.v.last() {
+                    Some(Str(ts)) => ts.s.ends_with(' '),
+                    _ => false,
+                };
+                if !after_space {
+                    self.word.push_char(' ', tag);
+                    self.wordlen += 1;
+                }
+            } else if c.is_whitespace() && !is_nonbreaking_space(c) {
                 /* Whitespace is mostly ignored, except to terminate words. */
                 self.flush_word();
                 self.spacetag = Some(tag.clone());
+            } else if c == '\u{ad}' {
+                /* A soft hyphen is only shown if the word is broken there. */
+                self.word_shy.push(self.wordlen);
+            } else if is_nonbreaking_space(c) {
+                /* Part of the word, but shown as an ordinary space. */
+                self.word.push_char(' ', tag);
+                self.wordlen += 1;
             } else if let Some(charwidth) = UnicodeWidthChar::width(c) {
                 /* Not whitespace; add to the current word. */
                 self.word.push_char(c, tag);
@@ -445,7 +723,14 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
         }
     }
 
-    pub fn add_preformatted_text(&mut self, text: &str, tag_main: &T, tag_wrapped: &T) {
+    pub fn add_preformatted_text(
+        &mut self,
+        text: &str,
+        tab_stop: usize,
+        mut line_numbers: Option<&mut LineNumbers>,
+        tag_main: &T,
+        tag_wrapped: &T,
+    ) {
         html_trace!(
             "WrappedBlock::add_preformatted_text({}), {:?}/{:?}",
             text,
@@ -456,11 +741,25 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
         // bypass the word buffer.
         self.flush_word();
 
+        // Tab stops are counted from after the line numbers.  Lines which
+        // continue one that didn't fit only get a gutter if there is room
+        // for something after it.
+        let gutter = line_numbers.as_ref().map_or(0, |numbers| numbers.width + 1);
+        let gutter_fits = gutter < self.width;
+        let mut indent = 0;
         for c in text.chars() {
+            if self.linelen == 0 {
+                indent = 0;
+                if let Some(numbers) = line_numbers.as_mut() {
+                    self.add_gutter(numbers, c == '\n', tag_main);
+                    indent = gutter;
+                }
+            }
             if let Some(charwidth) = UnicodeWidthChar::width(c) {
                 if self.linelen + charwidth > self.width {
                     self.flush_line();
                     self.pre_wrapped = true;
+                    indent = self.add_continuation_gutter(&mut line_numbers, gutter_fits, tag_main);
                 }
                 self.line.push_char(
                     c,
@@ -478,11 +777,16 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
                         self.pre_wrapped = false;
                     }
                     '\t' => {
-                        let tab_stop = 8;
                         let mut at_least_one_space = false;
-                        while self.linelen % tab_stop != 0 || !at_least_one_space {
+                        while (self.linelen - indent) % tab_stop != 0 || !at_least_one_space {
                             if self.linelen >= self.width {
                                 self.flush_line();
+                                self.pre_wrapped = true;
+                                indent = self.add_continuation_gutter(
+                                    &mut line_numbers,
+                                    gutter_fits,
+                                    tag_main,
+                                );
                             } else {
                                 self.line.push_char(
                                     ' ',
@@ -504,6 +808,50 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
         }
     }
 
+    /// Add a blank gutter to a line continuing one which didn't fit, if
+    /// there are line numbers and the gutter leaves room for some text,
+    /// and return the width added.
+    fn add_continuation_gutter(
+        &mut self,
+        line_numbers: &mut Option<&mut LineNumbers>,
+        gutter_fits: bool,
+        tag: &T,
+    ) -> usize {
+        match line_numbers.as_mut() {
+            Some(numbers) if gutter_fits => {
+                self.add_gutter(numbers, false, tag);
+                numbers.width + 1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Start a preformatted line with its line number right-aligned in
+    /// the gutter, or a blank gutter if it continues a line which didn't
+    /// fit.  The gap after the number is left off an empty line.
+    fn add_gutter(&mut self, numbers: &mut LineNumbers, empty_line: bool, tag: &T) {
+        let number = if self.pre_wrapped {
+            String::new()
+        } else {
+            numbers.next += 1;
+            (numbers.next - 1).to_string()
+        };
+        let mut gutter = format!("{:>width$} ", number, width = numbers.width);
+        if empty_line {
+            gutter.truncate(gutter.trim_end().len());
+        }
+        self.linelen += gutter.len();
+        self.line.push_str(TaggedString {
+            s: gutter,
+            tag: tag.clone(),
+        });
+    }
+
+    /// Allow the current word to be broken here, without a hyphen.
+    pub fn add_break_opportunity(&mut self) {
+        self.word_wbr.push(self.wordlen);
+    }
+
     pub fn add_element(&mut self, elt: TaggedLineElement<T>) {
         self.word.push(elt);
     }
@@ -529,48 +877,228 @@ impl<T: Clone + Eq + Debug + Default> WrappedBlock<T> {
 /// text.  This can be anything from `()` as for `PlainDecorator` or a more
 /// featured type such as `RichAnnotation`.  The annotated spans (`TaggedLine`)
 /// can be used by application code to add e.g. terminal colours or underlines.
+///
+/// Only [`make_subblock_decorator`](TextDecorator::make_subblock_decorator)
+/// has to be implemented; by default nothing is added around inline
+/// markup, and blocks get plain text prefixes.
+///
+/// ```rust
+/// use html2text::render::text_renderer::TextDecorator;
+///
+/// struct Underscores;
+///
+/// impl TextDecorator for Underscores {
+///     type Annotation = ();
+///
+///     fn decorate_em_start(&mut self) -> (String, ()) {
+///         ("_".to_string(), ())
+///     }
+///
+///     fn decorate_em_end(&mut self) -> String {
+///         "_".to_string()
+///     }
+///
+///     fn make_subblock_decorator(&self) -> Self {
+///         Underscores
+///     }
+/// }
+///
+/// let html = br#"<p>Some <em>very</em> <b>nice</b> <a href="x">text</a></p>"#;
+/// let text = html2text::from_read_with_decorator(&html[..], 80, Underscores);
+/// assert_eq!(text, "Some _very_ nice text\n");
+/// ```
 pub trait TextDecorator {
     /// An annotation which can be added to text, and which will
     /// be attached to spans of text.
     type Annotation: Eq + PartialEq + Debug + Clone + Default;
 
     /// Return an annotation and rendering prefix for a link.
-    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation);
+    #[allow(unused_variables)]
+    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
 
     /// Return a suffix for after a link.
-    fn decorate_link_end(&mut self) -> String;
+    fn decorate_link_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return a prefix for a link shown with `LinkStyle::Inline`.
+    fn decorate_inline_link_start(&mut self, _url: &str) -> String {
+        "[".to_string()
+    }
+
+    /// Return a suffix for a link shown with `LinkStyle::Inline`,
+    /// including its target.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::{LinkStyle, TextDecorator};
+    ///
+    /// struct Angled;
+    ///
+    /// impl TextDecorator for Angled {
+    ///     type Annotation = ();
+    ///
+    ///     fn decorate_inline_link_start(&mut self, _url: &str) -> String {
+    ///         String::new()
+    ///     }
+    ///
+    ///     fn decorate_inline_link_end(&mut self, url: &str) -> String {
+    ///         format!(" <{}>", url)
+    ///     }
+    ///
+    ///     fn make_subblock_decorator(&self) -> Self {
+    ///         Angled
+    ///     }
+    /// }
+    ///
+    /// let html = br#"<p>See <a href="http://x/">the site</a>.</p>"#;
+    /// let text = html2text::config::Config::with_decorator(Angled)
+    ///     .link_style(LinkStyle::Inline)
+    ///     .convert(&html[..]);
+    /// assert_eq!(text, "See the site <http://x/>.\n");
+    /// ```
+    fn decorate_inline_link_end(&mut self, url: &str) -> String {
+        format!("]({})", url)
+    }
+
+    /// Return the text and annotation for a citation link (such as
+    /// `[1]` in a `<sup>`).  By default it's shown like any other link.
+    fn decorate_citation(&mut self, url: &str, text: &str) -> (String, Self::Annotation) {
+        let (start, annotation) = self.decorate_link_start(url);
+        let end = self.decorate_link_end();
+        (format!("{}{}{}", start, text, end), annotation)
+    }
 
     /// Return an annotation and rendering prefix for em
-    fn decorate_em_start(&mut self) -> (String, Self::Annotation);
+    fn decorate_em_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
 
     /// Return a suffix for after an em.
-    fn decorate_em_end(&mut self) -> String;
+    fn decorate_em_end(&mut self) -> String {
+        String::new()
+    }
 
     /// Return an annotation and rendering prefix for strong
-    fn decorate_strong_start(&mut self) -> (String, Self::Annotation);
+    fn decorate_strong_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
 
     /// Return a suffix for after an strong.
-    fn decorate_strong_end(&mut self) -> String;
+    fn decorate_strong_end(&mut self) -> String {
+        String::new()
+    }
 
     /// Return an annotation and rendering prefix for strikeout
-    fn decorate_strikeout_start(&mut self) -> (String, Self::Annotation);
+    fn decorate_strikeout_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
 
     /// Return a suffix for after an strikeout.
-    fn decorate_strikeout_end(&mut self) -> String;
+    fn decorate_strikeout_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for underlined text.
+    fn decorate_underline_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
+
+    /// Return a suffix for after underlined text.
+    fn decorate_underline_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for de-emphasised
+    /// (`<small>`) text.
+    fn decorate_small_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
+
+    /// Return a suffix for after de-emphasised text.
+    fn decorate_small_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for highlighted text.
+    /// By default this is a `custom("mark", ..)` annotation; any
+    /// delimiters come from [`RenderOptions::mark_delimiter`].
+    fn decorate_mark_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), self.custom("mark", Vec::new()))
+    }
+
+    /// Return a suffix for after highlighted text.
+    fn decorate_mark_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for the contents of a
+    /// table header cell.
+    fn decorate_table_header_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
+
+    /// Return a suffix for after the contents of a table header cell.
+    fn decorate_table_header_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for an inline quotation.
+    /// By default the `cite` URL, if any, becomes a `custom("cite", ..)`
+    /// annotation.
+    fn decorate_quote_start(&mut self, cite: Option<&str>) -> (String, Self::Annotation) {
+        let annotation = match cite {
+            Some(cite) => self.custom("cite", vec![cite.to_string()]),
+            None => Self::Annotation::default(),
+        };
+        ("\"".to_string(), annotation)
+    }
+
+    /// Return a suffix for after an inline quotation.
+    fn decorate_quote_end(&mut self) -> String {
+        "\"".to_string()
+    }
 
     /// Return an annotation and rendering prefix for color
-    fn decorate_color_start(&mut self,color: crate::Color) -> (String, Self::Annotation);
+    #[allow(unused_variables)]
+    fn decorate_color_start(&mut self,color: crate::Color) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
     /// Return a suffix for after a colored region.
-    fn decorate_color_end(&mut self) -> String;
+    fn decorate_color_end(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return an annotation and rendering prefix for text with a
+    /// background colour.  By default this is a `custom("bgcolor", ..)`
+    /// annotation with the colour as `#rrggbb`.
+    fn decorate_bgcolor_start(&mut self, color: crate::Color) -> (String, Self::Annotation) {
+        let hex = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+        (String::new(), self.custom("bgcolor", vec![hex]))
+    }
+
+    /// Return a suffix for after a region with a background colour.
+    fn decorate_bgcolor_end(&mut self) -> String {
+        String::new()
+    }
     /// mark a non-break begin
-    fn mark_nobreak_start(&mut self) -> (String, Self::Annotation);
+    fn mark_nobreak_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
     /// mark a non-break end
-    fn mark_nobreak_end(&mut self) -> (String, Self::Annotation);
+    fn mark_nobreak_end(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
     /// Return an annotation and rendering prefix for code
-    fn decorate_code_start(&mut self) -> (String, Self::Annotation);
+    fn decorate_code_start(&mut self) -> (String, Self::Annotation) {
+        (String::new(), Self::Annotation::default())
+    }
 
     /// Return a suffix for after an code.
-    fn decorate_code_end(&mut self) -> String;
+    fn decorate_code_end(&mut self) -> String {
+        String::new()
+    }
     /// Return a mark for Start of Redacted Region
     #[allow(unused_variables)]
     fn decorate_redact_start(&self,psk: String, id: uuid::Uuid) -> (String, Self::Annotation){
@@ -582,28 +1110,143 @@ pub trait TextDecorator {
         (String::new(),Self::Annotation::default())
     }
     /// Return an annotation for the initial part of a preformatted line
-    fn decorate_preformat_first(&mut self) -> Self::Annotation;
+    fn decorate_preformat_first(&mut self) -> Self::Annotation {
+        Self::Annotation::default()
+    }
 
     /// Return an annotation for a continuation line when a preformatted
     /// line doesn't fit.
-    fn decorate_preformat_cont(&mut self) -> Self::Annotation;
+    fn decorate_preformat_cont(&mut self) -> Self::Annotation {
+        Self::Annotation::default()
+    }
 
     /// Return an annotation and rendering prefix for a link.
-    fn decorate_image(&mut self, src: &str, title: &str, w:usize ,h: usize) -> (String, Self::Annotation);
+    #[allow(unused_variables)]
+    fn decorate_image(&mut self, src: &str, title: &str, w:usize ,h: usize) -> (String, Self::Annotation) {
+        (format!("[{}]", title), Self::Annotation::default())
+    }
+
+    /// Return an annotation for text in an HTML element named `name`.
+    #[allow(unused_variables)]
+    fn decorate_element(&mut self, name: &str) -> Self::Annotation {
+        Self::Annotation::default()
+    }
+
+    /// Return the text shown for an `<audio>` or `<video>` (`kind`) with
+    /// source `src`.
+    fn media_placeholder(&mut self, kind: &str, src: &str) -> String {
+        format!("[{}: {}]", kind, src)
+    }
 
     /// 自定义类型，用字符串表示
-    fn custom(&mut self, src: &str,value: Vec<String>) -> Self::Annotation;
+    #[allow(unused_variables)]
+    fn custom(&mut self, src: &str,value: Vec<String>) -> Self::Annotation {
+        Self::Annotation::default()
+    }
+    /// Return the line to draw for a thematic break (`<hr>`), or `None`
+    /// to leave it out.
+    fn horizontal_rule(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Whether to draw tables as pipe tables (`| a | b |`, with a `|---|`
+    /// line under the first row) instead of with borders.
+    fn pipe_tables(&self) -> bool {
+        false
+    }
+
     /// Return prefix string of header in specific level.
-    fn header_prefix(&mut self, level: usize) -> String;
+    fn header_prefix(&mut self, level: usize) -> String {
+        "#".repeat(level) + " "
+    }
 
     /// Return prefix string of quoted block.
-    fn quote_prefix(&mut self) -> String;
+    fn quote_prefix(&mut self) -> String {
+        "> ".to_string()
+    }
 
     /// Return prefix string of unordered list item.
-    fn unordered_item_prefix(&mut self) -> String;
+    fn unordered_item_prefix(&mut self) -> String {
+        "* ".to_string()
+    }
 
     /// Return prefix string of ith ordered list item.
-    fn ordered_item_prefix(&mut self, i: i64) -> String;
+    fn ordered_item_prefix(&mut self, i: i64) -> String {
+        format!("{}. ", i)
+    }
+
+    /// Return prefix string of an ordered list item numbered with
+    /// `marker`, such as a letter or Roman numeral.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::TextDecorator;
+    ///
+    /// struct Parens;
+    ///
+    /// impl TextDecorator for Parens {
+    ///     type Annotation = ();
+    ///
+    ///     fn ordered_item_marker_prefix(&mut self, marker: &str) -> String {
+    ///         format!("{}) ", marker)
+    ///     }
+    ///
+    ///     fn make_subblock_decorator(&self) -> Self {
+    ///         Parens
+    ///     }
+    /// }
+    ///
+    /// let html = br#"<ol type="a"><li>one</li><li>two</li></ol>"#;
+    /// let text = html2text::from_read_with_decorator(&html[..], 80, Parens);
+    /// assert_eq!(text, "a) one\nb) two\n");
+    /// ```
+    fn ordered_item_marker_prefix(&mut self, marker: &str) -> String {
+        format!("{}. ", marker)
+    }
+
+    /// Return prefix string of a task list item, in place of the bullet.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::TextDecorator;
+    ///
+    /// struct Boxes;
+    ///
+    /// impl TextDecorator for Boxes {
+    ///     type Annotation = ();
+    ///
+    ///     fn task_item_prefix(&mut self, checked: bool) -> String {
+    ///         if checked { "☑ " } else { "☐ " }.to_string()
+    ///     }
+    ///
+    ///     fn form_input(&mut self, _kind: &str, checked: bool) -> String {
+    ///         if checked { "☑" } else { "☐" }.to_string()
+    ///     }
+    ///
+    ///     fn make_subblock_decorator(&self) -> Self {
+    ///         Boxes
+    ///     }
+    /// }
+    ///
+    /// let html = br#"<ul><li><input type=checkbox checked> Done</li>
+    ///                <li><input type=checkbox> To do</li></ul>
+    ///                <p><input type=checkbox> Remember me</p>"#;
+    /// let text = html2text::from_read_with_decorator(&html[..], 80, Boxes);
+    /// assert_eq!(text, "☑ Done\n☐ To do\n\n☐ Remember me\n");
+    /// ```
+    fn task_item_prefix(&mut self, checked: bool) -> String {
+        if checked { "[x] " } else { "[ ] " }.to_string()
+    }
+
+    /// Return the text shown for a checkbox or radio button (`kind`)
+    /// form input.
+    fn form_input(&mut self, kind: &str, checked: bool) -> String {
+        match (kind, checked) {
+            ("radio", true) => "(•)",
+            ("radio", false) => "( )",
+            (_, true) => "[x]",
+            (_, false) => "[ ]",
+        }
+        .to_string()
+    }
 
     /// Return a new decorator of the same type which can be used
     /// for sub blocks.
@@ -611,7 +1254,10 @@ pub trait TextDecorator {
 
     /// Finish with a document, and return extra lines (eg footnotes)
     /// to add to the rendered text.
-    fn finalise(&mut self, links: Vec<String>) -> Vec<TaggedLine<Self::Annotation>>;
+    #[allow(unused_variables)]
+    fn finalise(&mut self, links: Vec<LinkRef>) -> Vec<TaggedLine<Self::Annotation>> {
+        Vec::new()
+    }
 }
 
 /// A space on a horizontal row.
@@ -630,29 +1276,179 @@ pub enum BorderSegHoriz {
     StraightVert,
 }
 
+/// The set of characters used to draw table borders.
+///
+/// The default is `Unicode`; earlier versions drew the horizontal
+/// borders with `-` and `+` and the vertical ones with `│`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BorderStyle {
+    /// Unicode box-drawing characters (`─`, `│`, `┼` and friends).
+    Unicode,
+    /// Plain ASCII (`-`, `|` and `+`), for terminals or pipelines which
+    /// can't cope with anything else.
+    Ascii,
+    /// No visible borders; columns are separated by a space and no
+    /// horizontal lines are drawn between rows.
+    None,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        BorderStyle::Unicode
+    }
+}
+
+impl BorderStyle {
+    /// The character used for a vertical line between columns.
+    pub fn vertical(self) -> char {
+        match self {
+            BorderStyle::Unicode => '│',
+            BorderStyle::Ascii => '|',
+            BorderStyle::None => ' ',
+        }
+    }
+
+    /// The character used to draw one segment of a horizontal line.
+    pub fn horizontal(self, seg: BorderSegHoriz) -> char {
+        use self::BorderSegHoriz::*;
+        match self {
+            BorderStyle::Unicode => match seg {
+                Straight => '─',
+                StraightVert => '/',
+                JoinAbove => '┴',
+                JoinBelow => '┬',
+                JoinCross => '┼',
+            },
+            BorderStyle::Ascii => match seg {
+                Straight => '-',
+                StraightVert => '|',
+                JoinAbove | JoinBelow | JoinCross => '+',
+            },
+            BorderStyle::None => ' ',
+        }
+    }
+
+    /// The character used to draw one segment of the line below a
+    /// table's header rows.
+    pub fn header_horizontal(self, seg: BorderSegHoriz) -> char {
+        use self::BorderSegHoriz::*;
+        match (self, seg) {
+            (BorderStyle::Unicode, Straight) => '═',
+            (BorderStyle::Unicode, JoinAbove) => '╧',
+            (BorderStyle::Unicode, JoinBelow) => '╤',
+            (BorderStyle::Unicode, JoinCross) => '╪',
+            (BorderStyle::Ascii, Straight) => '=',
+            _ => self.horizontal(seg),
+        }
+    }
+}
+
+/// Where to put a caption relative to the content it describes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaptionPosition {
+    /// Before the content.
+    Above,
+    /// After the content.
+    Below,
+}
+
+impl Default for CaptionPosition {
+    fn default() -> Self {
+        CaptionPosition::Below
+    }
+}
+
+/// How to mark headings.
+///
+/// ```rust
+/// use html2text::render::text_renderer::HeadingStyle;
+///
+/// let html = b"<h1>Title</h1><h2>Part one</h2><h3>Detail</h3>";
+/// let convert = |style| html2text::config::plain().heading_style(style).convert(&html[..]);
+/// assert_eq!(convert(HeadingStyle::Prefix), "# Title\n\n## Part one\n\n### Detail\n");
+/// assert_eq!(
+///     convert(HeadingStyle::Setext),
+///     "Title\n=====\n\nPart one\n--------\n\n### Detail\n"
+/// );
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeadingStyle {
+    /// A prefix chosen by the decorator, such as `# ` or `## `.
+    Prefix,
+    /// Underline `h1` with `=` and `h2` with `-`, as in Setext or
+    /// Markdown.  Deeper headings fall back to the prefix.
+    Setext,
+}
+
+impl Default for HeadingStyle {
+    fn default() -> Self {
+        HeadingStyle::Prefix
+    }
+}
+
+/// How to show the target of a link.
+///
+/// ```rust
+/// use html2text::render::text_renderer::LinkStyle;
+///
+/// let html = br#"<p>See <a href="http://x/">the site</a>.</p>"#;
+/// let convert = |style| html2text::config::plain().link_style(style).convert(&html[..]);
+/// assert_eq!(convert(LinkStyle::Footnote), "See [the site][1].\n\n[1]: http://x/\n");
+/// assert_eq!(convert(LinkStyle::Inline), "See [the site](http://x/).\n");
+/// assert_eq!(convert(LinkStyle::None), "See the site.\n");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// As the decorator chooses; for `PlainDecorator` this is a numbered
+    /// reference (`[text][1]`) with the URLs listed after the text.
+    Footnote,
+    /// Markdown-style, with the URL straight after the text:
+    /// `[text](url)`.
+    Inline,
+    /// Just the link text, dropping the URL.
+    None,
+}
+
+impl Default for LinkStyle {
+    fn default() -> Self {
+        LinkStyle::Footnote
+    }
+}
+
 /// A dividing line between table rows which tracks intersections
 /// with vertical lines.
 #[derive(Clone, Debug)]
 pub struct BorderHoriz {
     /// The segments for the line.
     pub segments: Vec<BorderSegHoriz>,
+    /// The characters to draw the line with.
+    pub style: BorderStyle,
+    /// True for the line below a table's header rows, which is drawn
+    /// doubled.
+    pub header: bool,
 }
 
 impl BorderHoriz {
     /// Create a new blank border line.
     pub fn new(width: usize) -> BorderHoriz {
-        BorderHoriz {
-            segments: vec![BorderSegHoriz::Straight; width],
-        }
+        BorderHoriz::new_type(width, BorderSegHoriz::Straight)
     }
 
     /// Create a new blank border line.
     pub fn new_type(width: usize, linetype: BorderSegHoriz) -> BorderHoriz {
         BorderHoriz {
             segments: vec![linetype; width],
+            style: BorderStyle::default(),
+            header: false,
         }
     }
 
+    /// Set the style used to draw this line.
+    pub fn with_style(mut self, style: BorderStyle) -> BorderHoriz {
+        self.style = style;
+        self
+    }
+
     /// Stretch the line to at least the specified width
     pub fn stretch_to(&mut self, width: usize) {
         use self::BorderSegHoriz::*;
@@ -715,26 +1511,28 @@ impl BorderHoriz {
     /// just above this line.
     pub fn to_vertical_lines_above(&self) -> String {
         use self::BorderSegHoriz::*;
+        let vertical = self.style.vertical();
         self.segments
             .iter()
             .map(|seg| match *seg {
                 Straight | JoinBelow | StraightVert => ' ',
-                JoinAbove | JoinCross => '│',
+                JoinAbove | JoinCross => vertical,
             })
             .collect()
     }
 
     /// Turn into a string with drawing characters
     pub fn into_string(self) -> String {
+        let style = self.style;
+        let header = self.header;
         self.segments
             .into_iter()
-            .map(|seg| match seg {
-                // table 样式
-                BorderSegHoriz::Straight => '-',
-                BorderSegHoriz::StraightVert => '|',
-                BorderSegHoriz::JoinAbove => '+',
-                BorderSegHoriz::JoinBelow => '+',
-                BorderSegHoriz::JoinCross => '+',
+            .map(|seg| {
+                if header {
+                    style.header_horizontal(seg)
+                } else {
+                    style.horizontal(seg)
+                }
             })
             .collect::<String>()
     }
@@ -791,11 +1589,129 @@ impl<T: PartialEq + Eq + Clone + Debug + Default> RenderLine<T> {
     }
 }
 
+/// Options controlling how a `SubRenderer` lays out its text.  These are
+/// inherited by any sub-renderers created for nested blocks.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// The characters used to draw table borders.
+    pub border_style: BorderStyle,
+    /// If set, a line to put before and after each preformatted block,
+    /// for example `"```"` to fence it like Markdown.
+    pub pre_fence: Option<String>,
+    /// If true, rejoin words which were split with a hyphen at a line
+    /// break in the source text (e.g. `exam-\nple` becomes `example`).
+    pub join_hyphenated: bool,
+    /// The bullet characters for unordered lists, cycling through them
+    /// by nesting depth.  If empty, the decorator's bullet is used at
+    /// every level.
+    pub bullets: Vec<char>,
+    /// If set, the prefix for each line of a blockquote instead of the
+    /// decorator's (usually `"> "`).
+    pub quote_prefix: Option<String>,
+    /// Where to put a `<figcaption>` relative to the rest of its figure.
+    pub figure_caption: CaptionPosition,
+    /// How to mark headings.
+    pub heading_style: HeadingStyle,
+    /// If true, approximate CSS vertical margins and padding from inline
+    /// `style` attributes with extra blank lines (roughly one per `em`).
+    pub css_margins: bool,
+    /// If set, stop rendering after this many document nodes, returning
+    /// whatever was rendered so far.  This bounds the work done for
+    /// pathological or hostile input.
+    pub max_nodes: Option<usize>,
+    /// If set, keep only this many lines of output (including any list
+    /// of links), followed by an ellipsis line if anything was cut.
+    pub max_lines: Option<usize>,
+    /// The text of the line added when output is cut short by
+    /// `max_lines`, instead of `"…"`.
+    pub ellipsis: Option<String>,
+    /// If true, show control characters (other than tab and newline)
+    /// from the document as U+FFFD REPLACEMENT CHARACTER rather than
+    /// dropping them.  Either way they never reach the output, where
+    /// they could be interpreted as terminal escapes.
+    pub replace_control_chars: bool,
+    /// If set, render `<br>` as this text (e.g. `" / "`) instead of
+    /// starting a new line.
+    pub line_break: Option<String>,
+    /// If set, show non-breaking spaces as this character (e.g. `'␣'`)
+    /// instead of as a space, which can help when debugging layout.
+    pub nbsp_marker: Option<char>,
+    /// How to show link targets.
+    pub link_style: LinkStyle,
+    /// If true, render links as just their text, without the link
+    /// target or any annotation.
+    pub ignore_links: bool,
+    /// If true, escape `<`, `>` and `&` as HTML entities in the final
+    /// string, so that it can be put back inside HTML (e.g. a `<pre>`).
+    /// Wrapping is done on the unescaped text.
+    pub escape_html: bool,
+    /// If true, render images without a size like links to their source,
+    /// using the alt text (or `image`) as the link text.
+    pub image_links: bool,
+    /// If set, the text shown for an image with no alt text, instead of
+    /// the decorator's.
+    pub image_placeholder: Option<String>,
+    /// If set, the text shown for an image with alt text, with `{alt}`
+    /// and `{src}` replaced by the alt text and source.
+    pub image_format: Option<String>,
+    /// How many cells a line may run past the width, to keep a word on
+    /// the line rather than wrapping it.  Table cells never overrun.
+    pub width_slack: usize,
+    /// If true, render a table's `<tfoot>` rows at the end of the table,
+    /// rather than where they appear in the source.
+    pub tfoot_last: bool,
+    /// If set, put this text either side of highlighted (`<mark>`) text,
+    /// e.g. `"=="`.
+    pub mark_delimiter: Option<String>,
+    /// The distance between tab stops in preformatted text; 8 if unset.
+    pub tab_width: Option<usize>,
+    /// The narrowest that a column of text is made before a table is laid
+    /// out vertically instead; 3 if unset.
+    pub min_width: Option<usize>,
+    /// If true, number the lines of preformatted blocks, right-aligned in
+    /// a gutter to the left of the text.
+    pub pre_line_numbers: bool,
+    /// The number of spaces to leave either side of the separator between
+    /// table columns.
+    pub column_gap: usize,
+    /// If true, annotate the text of links with their `rel` and `target`
+    /// attributes, as `custom("rel", ..)` and `custom("target", ..)`.
+    pub link_metadata: bool,
+    /// If true, join the whole string output into one line, with a single
+    /// space between the words.  Table borders and column separators are
+    /// left out.
+    pub single_line: bool,
+    /// If true, number figure captions through the document as
+    /// `Figure 1: `, `Figure 2: ` and so on.
+    pub number_figures: bool,
+    /// If true, leave out trailing spaces in the prefix of a blank line,
+    /// so that an empty line in a quote is just `>`.
+    pub trim_blank_prefixes: bool,
+    /// If true, keep runs of spaces and newlines in ordinary text as they
+    /// are, like CSS `white-space: pre-wrap`, instead of collapsing them.
+    pub preserve_whitespace: bool,
+    /// If true, remove trailing whitespace, such as the padding of table
+    /// cells, from the end of each output line.
+    pub trim_trailing_whitespace: bool,
+    /// If true, leave out the newline at the end of the string output.
+    pub no_trailing_newline: bool,
+    /// If true, keep every blank line between blocks, rather than
+    /// collapsing runs of them (e.g. from empty paragraphs) into one.
+    pub keep_blank_lines: bool,
+    /// If true, annotate text with the HTML elements it came from, such
+    /// as `RichAnnotation::Element("h1")` with the rich decorator.
+    pub element_annotations: bool,
+    /// If true, number the items of nested ordered lists after the items
+    /// they're in, e.g. `1.2.`, rather than starting again from `1.`.
+    pub hierarchical_ol: bool,
+}
+
 /// A renderer which just outputs plain text with
 /// annotations depending on a decorator.
 #[derive(Clone)]
 pub struct SubRenderer<D: TextDecorator> {
     width: usize,
+    options: RenderOptions,
     lines: LinkedList<RenderLine<Vec<D::Annotation>>>,
     /// True at the end of a block, meaning we should add
     /// a blank line if any other text is added.
@@ -803,51 +1719,154 @@ pub struct SubRenderer<D: TextDecorator> {
     wrapping: Option<WrappedBlock<Vec<D::Annotation>>>,
     decorator: D,
     ann_stack: Vec<D::Annotation>,
+    /// For each annotation started, whether it was pushed onto
+    /// `ann_stack` (it isn't if an identical one is already there).
+    ann_pushed: Vec<bool>,
     text_filter_stack: Vec<fn(&str) -> Option<String>>,
     /// The depth of <pre> block stacking.
     pre_depth: usize,
+    /// The line numbers of the current preformatted block, if enabled.
+    line_numbers: Option<LineNumbers>,
+    /// The number of unordered lists this renderer is nested in.
+    list_depth: usize,
+    /// The numbers of the ordered list items this renderer is nested in,
+    /// such as `"1.2."`, for hierarchical numbering.
+    ol_context: String,
+    /// The number of no-break regions this renderer is nested in.  Only
+    /// the outermost one is marked, as they can't be nested.
+    nobreak_depth: usize,
+    /// The number of regions (e.g. `<nobr>`) this renderer is nested in
+    /// whose text mustn't be wrapped.
+    nowrap_depth: usize,
+    /// The number of `white-space: pre-wrap` regions this renderer is
+    /// nested in.
+    pre_wrap_depth: usize,
+    /// The targets of the links currently open, for `LinkStyle::Inline`.
+    link_targets: Vec<String>,
+    /// The alignment of the lines of text being added.
+    alignment: crate::Alignment,
 }
 
 impl<D: TextDecorator + Debug> std::fmt::Debug for SubRenderer<D> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.debug_struct("SubRenderer")
             .field("width", &self.width)
+            .field("options", &self.options)
             .field("lines", &self.lines)
             .field("decorator", &self.decorator)
             .field("ann_stack", &self.ann_stack)
             .field("pre_depth", &self.pre_depth)
+            .field("list_depth", &self.list_depth)
+            .field("ol_context", &self.ol_context)
+            .field("nobreak_depth", &self.nobreak_depth)
+            .field("nowrap_depth", &self.nowrap_depth)
+            .field("pre_wrap_depth", &self.pre_wrap_depth)
+            .field("link_targets", &self.link_targets)
+            .field("alignment", &self.alignment)
             .finish()
     }
 }
 
 impl<D: TextDecorator> SubRenderer<D> {
     /// Render links as lines
-    pub fn finalise(&mut self, links: Vec<String>) -> Vec<TaggedLine<D::Annotation>> {
+    pub fn finalise(&mut self, links: Vec<LinkRef>) -> Vec<TaggedLine<D::Annotation>> {
         self.decorator.finalise(links)
     }
 
     /// Construct a new empty SubRenderer.
     pub fn new(width: usize, decorator: D) -> SubRenderer<D> {
+        SubRenderer::new_with_options(width, RenderOptions::default(), decorator)
+    }
+
+    /// Construct a new empty SubRenderer with the given options.
+    pub fn new_with_options(width: usize, options: RenderOptions, decorator: D) -> SubRenderer<D> {
         html_trace!("new({})", width);
         SubRenderer {
             width,
+            options,
             lines: LinkedList::new(),
             at_block_end: false,
             wrapping: None,
             decorator,
             ann_stack: Vec::new(),
+            ann_pushed: Vec::new(),
             pre_depth: 0,
+            line_numbers: None,
+            list_depth: 0,
+            ol_context: String::new(),
+            nobreak_depth: 0,
+            nowrap_depth: 0,
+            pre_wrap_depth: 0,
+            link_targets: Vec::new(),
+            alignment: Default::default(),
             text_filter_stack: Vec::new(),
         }
     }
 
-    fn ensure_wrapping_exists(&mut self) {
-        if self.wrapping.is_none() {
-            self.wrapping = Some(WrappedBlock::new(self.width));
+    /// Make sure there are at least `count` blank lines at this point
+    /// (e.g. for CSS margins), without changing the usual spacing between
+    /// blocks.
+    pub fn add_blank_lines(&mut self, count: usize) {
+        self.flush_all();
+        // Adjoining margins collapse into the larger one, as in CSS.
+        let existing = self
+            .lines
+            .iter()
+            .rev()
+            .take_while(|line| match line {
+                RenderLine::Text(tline) => tline.width() == 0,
+                RenderLine::Line(_) => false,
+            })
+            .count();
+        for _ in existing..count {
+            self.lines.push_back(RenderLine::Text(TaggedLine::new()));
         }
     }
 
-    /// Get the current line wrapping context (and create if
+    /// Mark this renderer as holding the contents of an unordered
+    /// list item, so that nested lists use the next bullet.
+    pub fn enter_unordered_list(&mut self) {
+        self.list_depth += 1;
+    }
+
+    /// The numbers of the ordered list items this renderer is nested in,
+    /// such as `"1.2."`.
+    pub fn ordered_list_context(&self) -> &str {
+        &self.ol_context
+    }
+
+    /// Mark this renderer as holding the contents of the ordered list
+    /// item with marker `marker` (e.g. `"2"`).
+    pub fn enter_ordered_list_item(&mut self, marker: &str) {
+        self.ol_context.push_str(marker);
+        self.ol_context.push('.');
+    }
+
+    /// Add an empty string with `annotation` at the current point in the
+    /// text, to mark the start or end of a region which isn't wrapped.
+    fn add_nowrap_marker(&mut self, annotation: D::Annotation) {
+        if self.at_block_end {
+            self.start_block();
+        }
+        let mut tag = self.ann_stack.clone();
+        tag.push(annotation);
+        self.ensure_wrapping_exists();
+        self.wrapping
+            .as_mut()
+            .unwrap()
+            .add_element(TaggedLineElement::Str(TaggedString {
+                s: String::new(),
+                tag,
+            }));
+    }
+
+    fn ensure_wrapping_exists(&mut self) {
+        if self.wrapping.is_none() {
+            self.wrapping = Some(WrappedBlock::new(self.width, self.options.width_slack));
+        }
+    }
+
+    /// Get the current line wrapping context (and create if
     /// needed).
     fn current_text(&mut self) -> &mut WrappedBlock<Vec<D::Annotation>> {
         self.ensure_wrapping_exists();
@@ -872,9 +1891,25 @@ impl<D: TextDecorator> SubRenderer<D> {
 
     /// Flushes the current wrapped block into the lines.
     fn flush_wrapping(&mut self) {
+        use crate::Alignment;
+
         if let Some(w) = self.wrapping.take() {
-            self.lines
-                .extend(w.into_lines().into_iter().map(RenderLine::Text))
+            let width = self.width;
+            let alignment = self.alignment;
+            self.lines.extend(w.into_lines().into_iter().map(|mut line| {
+                let spare = width.saturating_sub(line.width());
+                let indent = match alignment {
+                    Alignment::Left => 0,
+                    Alignment::Center => spare / 2,
+                    Alignment::Right => spare,
+                };
+                if indent > 0 {
+                    let mut aligned = TaggedLine::from_string(" ".repeat(indent), &Vec::new());
+                    aligned.consume(&mut line);
+                    line = aligned;
+                }
+                RenderLine::Text(line)
+            }))
         }
     }
 
@@ -889,10 +1924,44 @@ impl<D: TextDecorator> SubRenderer<D> {
         let mut result = String::new();
         #[cfg(feature = "html_trace")]
         let width: usize = self.width;
+        let escape_html = self.options.escape_html;
+        let single_line = self.options.single_line;
+        let trim_trailing = self.options.trim_trailing_whitespace;
+        let no_trailing_newline = self.options.no_trailing_newline;
         for line in self.into_lines() {
-            result.push_str(&line.into_string());
+            if single_line {
+                if let RenderLine::Line(_) = line {
+                    continue;
+                }
+            }
+            let text = if escape_html {
+                escape_html_text(&line.into_string())
+            } else {
+                line.into_string()
+            };
+            let text = if trim_trailing {
+                text.trim_end()
+            } else {
+                &text
+            };
+            if single_line {
+                for word in text.split_whitespace() {
+                    if !result.is_empty() {
+                        result.push(' ');
+                    }
+                    result.push_str(word);
+                }
+            } else {
+                result.push_str(text);
+                result.push('\n');
+            }
+        }
+        if single_line && !result.is_empty() {
             result.push('\n');
         }
+        if no_trailing_newline && result.ends_with('\n') {
+            result.pop();
+        }
         html_trace!("into_string({}, {:?})", width, result);
         result
     }
@@ -908,7 +1977,22 @@ impl<D: TextDecorator> SubRenderer<D> {
         result
     }
 
-    /// Wrap links to width
+    /// Wrap links to width.  A link too long for a line is broken after a
+    /// `/`, `?`, `&` or `.` if possible, and only split anywhere if
+    /// there's no such place on the line.
+    ///
+    /// ```rust
+    /// let html = br#"<a href="http://www.example.com/some/long/path?query=1&amp;other=two">x</a>"#;
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 20),
+    ///     "[x][1]\n\n[1]: http://www.\nexample.com/some/\nlong/path?query=1&\nother=two\n"
+    /// );
+    /// let html = br#"<a href="http://abcdefghijklmnopqrstuvwxyz">x</a>"#;
+    /// assert_eq!(
+    ///     html2text::from_read(&html[..], 20),
+    ///     "[x][1]\n\n[1]: http://\nabcdefghijklmnopqrst\nuvwxyz\n"
+    /// );
+    /// ```
     pub fn fmt_links(&mut self, mut links: Vec<TaggedLine<D::Annotation>>) {
         for line in links.drain(..) {
             /* Hard wrap */
@@ -917,30 +2001,39 @@ impl<D: TextDecorator> SubRenderer<D> {
             for ts in line.into_tagged_strings() {
                 // FIXME: should we percent-escape?  This is probably
                 // an invalid URL to start with.
-                let s = ts.s.replace('\n', " ");
+                let s = sanitise_control_chars(&ts.s, self.options.replace_control_chars).replace('\n', " ");
                 let tag = vec![ts.tag];
 
                 let width = s.width();
                 if pos + width > self.width {
                     // split the string and start a new line
                     let mut buf = String::new();
+                    // Where buf could be broken after a separator.
+                    let mut break_at = None;
                     for c in s.chars() {
                         let c_width = UnicodeWidthChar::width(c).unwrap_or(0);
                         if pos + c_width > self.width {
+                            let rest = match break_at.take() {
+                                Some(idx) => buf.split_off(idx),
+                                None => String::new(),
+                            };
                             if !buf.is_empty() {
                                 wrapped_line.push_str(TaggedString {
                                     s: buf,
                                     tag: tag.clone(),
                                 });
-                                buf = String::new();
                             }
 
                             self.lines.push_back(RenderLine::Text(wrapped_line));
                             wrapped_line = TaggedLine::new();
-                            pos = 0;
+                            pos = rest.width();
+                            buf = rest;
                         }
                         pos += c_width;
                         buf.push(c);
+                        if matches!(c, '/' | '?' | '&' | '.') {
+                            break_at = Some(buf.len());
+                        }
                     }
                     wrapped_line.push_str(TaggedString { s: buf, tag });
                 } else {
@@ -961,12 +2054,274 @@ impl<D: TextDecorator> SubRenderer<D> {
         self.lines
     }
 
+    /// Keep only the first `max` lines, followed by a line of `ellipsis`
+    /// if any were dropped.  A cut inside a table is moved back to the
+    /// border between two rows, if there is one, or else to before the
+    /// table.  Blank lines just before the cut are dropped too.
+    pub fn truncate_lines(&mut self, max: usize, ellipsis: &str) {
+        self.flush_wrapping();
+        if self.lines.len() <= max {
+            return;
+        }
+        let lines: Vec<_> = std::mem::take(&mut self.lines).into_iter().collect();
+        let is_border = |line: &RenderLine<_>| matches!(line, RenderLine::Line(_));
+        let is_blank = |line: &RenderLine<_>| matches!(line, RenderLine::Text(tline) if tline.width() == 0);
+        let mut keep = max;
+        if !is_border(&lines[keep]) && lines[keep..].iter().take_while(|l| !is_blank(l)).any(is_border) {
+            if let Some(i) = lines[..keep].iter().rposition(|l| is_border(l) || is_blank(l)) {
+                if is_border(&lines[i]) {
+                    keep = i + 1;
+                    // Don't keep a table's top border without any rows.
+                    if i == 0 || is_blank(&lines[i - 1]) {
+                        keep = i;
+                    }
+                }
+            }
+        }
+        while keep > 0 && is_blank(&lines[keep - 1]) {
+            keep -= 1;
+        }
+        self.lines = lines.into_iter().take(keep).collect();
+        self.lines
+            .push_back(RenderLine::Text(TaggedLine::from_string(ellipsis.to_string(), &Vec::new())));
+    }
+
+    /// Remove any blank lines from the end of the output so far.
+    pub fn trim_trailing_blank_lines(&mut self) {
+        self.flush_wrapping();
+        while let Some(RenderLine::Text(tline)) = self.lines.back() {
+            if tline.width() != 0 {
+                break;
+            }
+            self.lines.pop_back();
+        }
+    }
+
+    /// Return the width of the widest line rendered so far.
+    pub fn content_width(&mut self) -> usize {
+        self.flush_wrapping();
+        self.lines
+            .iter()
+            .map(|line| match *line {
+                RenderLine::Text(ref tline) => tline.width(),
+                RenderLine::Line(ref border) => border.segments.len(),
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The options this renderer was created with.
+    pub fn options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    /// Draw the last line, if it's a table border, as the line below a
+    /// table's header rows.
+    pub fn mark_header_border(&mut self) {
+        if let Some(RenderLine::Line(border)) = self.lines.back_mut() {
+            border.header = true;
+        }
+    }
+
+    /// Add the decorator's line for a thematic break (`<hr>`) as a block
+    /// of its own, if it has one.
+    pub fn add_horizontal_rule(&mut self) {
+        if let Some(rule) = self.decorator.horizontal_rule() {
+            self.start_block();
+            let tag = self.ann_stack.clone();
+            self.lines
+                .push_back(RenderLine::Text(TaggedLine::from_string(rule, &tag)));
+            self.end_block();
+        }
+    }
+
+    /// Whether tables are drawn as pipe tables rather than with borders.
+    pub fn pipe_tables(&self) -> bool {
+        self.decorator.pipe_tables()
+    }
+
+    /// Add a row of a pipe table, made of `cells` and the number of
+    /// columns each spans.  Each cell's lines are joined into one, since
+    /// a pipe table cell can't hold more, and a cell spanning several
+    /// columns is followed by empty ones.  A `header` row is followed by
+    /// the `|---|` separator line.
+    pub fn append_pipe_row(&mut self, cells: Vec<(Self, usize)>, header: bool) {
+        use self::TaggedLineElement::Str;
+
+        self.flush_wrapping();
+        let tag = self.ann_stack.clone();
+        let mut line = TaggedLine::from_string("|".to_string(), &tag);
+        let mut num_columns = 0;
+        for (cell, colspan) in cells {
+            line.push_char(' ', &tag);
+            let mut first = true;
+            for cell_line in cell.into_lines() {
+                if let RenderLine::Text(tline) = cell_line {
+                    if tline.width() == 0 {
+                        continue;
+                    }
+                    if !first {
+                        line.push_char(' ', &tag);
+                    }
+                    first = false;
+                    for ts in tline.into_tagged_strings() {
+                        line.push(Str(TaggedString {
+                            s: ts.s.replace('|', "\\|"),
+                            tag: ts.tag,
+                        }));
+                    }
+                }
+            }
+            line.push_str(TaggedString {
+                s: " |".to_string(),
+                tag: tag.clone(),
+            });
+            for _ in 1..colspan {
+                line.push_str(TaggedString {
+                    s: "  |".to_string(),
+                    tag: tag.clone(),
+                });
+            }
+            num_columns += colspan.max(1);
+        }
+        self.lines.push_back(RenderLine::Text(line));
+        if header {
+            let separator = "|".to_string() + &"---|".repeat(num_columns);
+            self.lines
+                .push_back(RenderLine::Text(TaggedLine::from_string(separator, &tag)));
+        }
+    }
+
+    /// The alignment of the lines of text being added.
+    pub fn alignment(&self) -> crate::Alignment {
+        self.alignment
+    }
+
+    /// Change the alignment of the lines of text from here on.
+    pub fn set_alignment(&mut self, alignment: crate::Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Change the characters used for table borders from here on.
+    pub fn set_border_style(&mut self, style: BorderStyle) {
+        self.options.border_style = style;
+    }
+
+    /// Change how far lines may overrun the width from here on.
+    pub fn set_width_slack(&mut self, slack: usize) {
+        self.options.width_slack = slack;
+    }
+
+    /// How many preformatted blocks this renderer is inside.
+    pub fn pre_depth(&self) -> usize {
+        self.pre_depth
+    }
+
+    /// Number the lines of the preformatted text from here on, leaving
+    /// room for numbers up to `num_lines`.
+    pub fn start_line_numbers(&mut self, num_lines: usize) {
+        self.line_numbers = Some(LineNumbers {
+            next: 1,
+            width: num_lines.to_string().len(),
+        });
+    }
+
+    /// Stop numbering preformatted lines.
+    pub fn end_line_numbers(&mut self) {
+        self.line_numbers = None;
+    }
+
+    /// Start annotating text with the decorator's `custom(name, values)`
+    /// annotation.
+    pub fn start_custom(&mut self, name: &str, values: Vec<String>) {
+        let annotation = self.decorator.custom(name, values);
+        self.push_annotation(annotation);
+    }
+
+    /// Finish a custom annotation started earlier.
+    pub fn end_custom(&mut self) {
+        self.pop_annotation();
+    }
+
+    /// Start annotating text with `annotation`.  Annotations already in
+    /// effect aren't repeated, so deeply nested markup doesn't build up
+    /// long annotation lists.
+    fn push_annotation(&mut self, annotation: D::Annotation) {
+        let pushed = !self.ann_stack.contains(&annotation);
+        if pushed {
+            self.ann_stack.push(annotation);
+        }
+        self.ann_pushed.push(pushed);
+    }
+
+    /// Finish the annotation most recently started.
+    fn pop_annotation(&mut self) {
+        if let Some(true) = self.ann_pushed.pop() {
+            self.ann_stack.pop();
+        }
+    }
+
+    /// Add the configured fence line (if any) around a preformatted block.
+    fn add_pre_fence(&mut self, language: Option<&str>) {
+        if let Some(mut fence) = self.options.pre_fence.clone() {
+            fence.push_str(language.unwrap_or(""));
+            if self.at_block_end {
+                self.start_block();
+            }
+            self.flush_all();
+            let tag = self.ann_stack.clone();
+            self.lines
+                .push_back(RenderLine::Text(TaggedLine::from_string(fence, &tag)));
+        }
+    }
+
     fn add_horizontal_line(&mut self, line: BorderHoriz) {
         self.flush_wrapping();
-        self.lines.push_back(RenderLine::Line(line));
+        if self.options.border_style != BorderStyle::None {
+            self.lines
+                .push_back(RenderLine::Line(line.with_style(self.options.border_style)));
+        }
     }
 }
 
+/// Escape the characters which are special in HTML text.
+fn escape_html_text(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Return true for the space characters which shouldn't be broken at
+/// when wrapping, such as U+00A0 NO-BREAK SPACE.  They are kept as part of
+/// the surrounding word, and output as a normal space.
+fn is_nonbreaking_space(c: char) -> bool {
+    matches!(c, '\u{a0}' | '\u{2007}' | '\u{202f}')
+}
+
+/// Drop the control characters other than tab and newline from `text`,
+/// or replace them with U+FFFD if `replace` is set.
+fn sanitise_control_chars(text: &str, replace: bool) -> Cow<'_, str> {
+    let unsafe_char = |c: char| c.is_control() && c != '\t' && c != '\n';
+    if !text.chars().any(unsafe_char) {
+        return Cow::Borrowed(text);
+    }
+    text.chars()
+        .filter_map(|c| match c {
+            c if !unsafe_char(c) => Some(c),
+            _ if replace => Some('\u{fffd}'),
+            _ => None,
+        })
+        .collect::<String>()
+        .into()
+}
+
 fn filter_text_strikeout(s: &str) -> Option<String> {
     let mut result = String::new();
     for c in s.chars() {
@@ -980,6 +2335,42 @@ fn filter_text_strikeout(s: &str) -> Option<String> {
     Some(result)
 }
 
+/// Remove hyphens (and the following line break) from words which were
+/// split across lines in the source, as often found in text extracted
+/// from PDFs.  Only a hyphen between two letters and followed by a
+/// newline is removed.
+fn join_hyphenated_words(s: &str) -> Option<String> {
+    if !s.contains('\n') {
+        return None;
+    }
+    let chars: Vec<char> = s.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '-' && i > 0 && chars[i - 1].is_alphabetic() {
+            // Look for optional spaces, a newline, then more spaces.
+            let mut j = i + 1;
+            while j < chars.len() && chars[j] != '\n' && chars[j].is_whitespace() {
+                j += 1;
+            }
+            if j < chars.len() && chars[j] == '\n' {
+                j += 1;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_alphabetic() {
+                    i = j;
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+        i += 1;
+    }
+    Some(result)
+}
+
 impl<D: TextDecorator> Renderer for SubRenderer<D> {
     type Annotation = D::Annotation;
     type Asset = Vec<String>;
@@ -993,13 +2384,29 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
     }
 
     fn new_sub_renderer(&self, width: usize) -> Self {
-        SubRenderer::new(width, self.decorator.make_subblock_decorator())
+        // Nothing fits in zero columns, so let deeply indented text
+        // overflow instead.
+        let mut sub = SubRenderer::new_with_options(
+            width.max(1),
+            self.options.clone(),
+            self.decorator.make_subblock_decorator(),
+        );
+        sub.list_depth = self.list_depth;
+        sub.ol_context = self.ol_context.clone();
+        sub.nobreak_depth = self.nobreak_depth;
+        sub.nowrap_depth = self.nowrap_depth;
+        sub.pre_wrap_depth = self.pre_wrap_depth;
+        sub
     }
 
     fn start_block(&mut self) {
         html_trace!("start_block({})", self.width);
         self.flush_all();
-        if !self.lines.is_empty() {
+        let after_blank = match self.lines.back() {
+            Some(RenderLine::Text(line)) => line.is_empty(),
+            _ => false,
+        };
+        if !self.lines.is_empty() && (self.options.keep_blank_lines || !after_blank) {
             self.add_empty_line();
         }
         html_trace_quiet!("start_block; at_block_end <- false");
@@ -1023,24 +2430,26 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
     }
 
     fn add_horizontal_border(&mut self) {
-        self.flush_wrapping();
-        self.lines
-            .push_back(RenderLine::Line(BorderHoriz::new(self.width)));
+        self.add_horizontal_line(BorderHoriz::new(self.width));
     }
 
     fn add_horizontal_border_width(&mut self, width: usize) {
-        self.flush_wrapping();
-        self.lines
-            .push_back(RenderLine::Line(BorderHoriz::new(width)));
+        self.add_horizontal_line(BorderHoriz::new(width));
     }
 
-    fn start_pre(&mut self) {
+    fn start_pre(&mut self, language: Option<&str>) {
+        if self.pre_depth == 0 {
+            self.add_pre_fence(language);
+        }
         self.pre_depth += 1;
     }
 
     fn end_pre(&mut self) {
         if self.pre_depth > 0 {
             self.pre_depth -= 1;
+            if self.pre_depth == 0 {
+                self.add_pre_fence(None);
+            }
         } else {
             panic!("Attempt to end a preformatted block which wasn't opened.");
         }
@@ -1052,9 +2461,19 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
 
     fn add_inline_text(&mut self, text: &str) {
         html_trace!("add_inline_text({}, {})", self.width, text);
-        if self.pre_depth == 0 && self.at_block_end && text.chars().all(char::is_whitespace) {
-            // Ignore whitespace between blocks.
-            return;
+        let pre_wrap =
+            self.pre_depth == 0 && (self.options.preserve_whitespace || self.pre_wrap_depth > 0);
+        if self.pre_depth == 0 && text.chars().all(char::is_whitespace) {
+            // Ignore whitespace between blocks, and whitespace which would
+            // start a block with preserved whitespace, which is just the
+            // indentation of the markup.
+            let block_empty = match self.wrapping {
+                Some(ref w) => w.text.is_empty() && w.is_empty(),
+                None => true,
+            };
+            if self.at_block_end || (pre_wrap && block_empty) {
+                return;
+            }
         }
         if self.at_block_end {
             self.start_block();
@@ -1074,18 +2493,47 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
         // When we stop supporting Rust < 1.40, this can become:
         //let filtered_text = s.as_deref().unwrap_or(text);
         let filtered_text = s.as_ref().map(Deref::deref).unwrap_or(text);
+        let sanitised = sanitise_control_chars(filtered_text, self.options.replace_control_chars);
+        let filtered_text = &*sanitised;
         if self.pre_depth == 0 {
-            self.wrapping
-                .as_mut()
-                .unwrap()
-                .add_text(filtered_text, &self.ann_stack);
+            let joined = if self.options.join_hyphenated {
+                join_hyphenated_words(filtered_text)
+            } else {
+                None
+            };
+            let filtered_text = match joined {
+                Some(ref joined) => joined,
+                None => filtered_text,
+            };
+            let marked = self.options.nbsp_marker.map(|marker| {
+                filtered_text
+                    .chars()
+                    .map(|c| if is_nonbreaking_space(c) { marker } else { c })
+                    .collect::<String>()
+            });
+            let filtered_text = match marked {
+                Some(ref marked) => marked,
+                None => filtered_text,
+            };
+            let wrapping = self.wrapping.as_mut().unwrap();
+            if self.nowrap_depth > 0 {
+                wrapping.add_text_nowrap(filtered_text, &self.ann_stack);
+            } else if pre_wrap {
+                let tab_stop = self.options.tab_width.unwrap_or(8).max(1);
+                wrapping.add_text_pre_wrap(filtered_text, tab_stop, &self.ann_stack);
+            } else {
+                wrapping.add_text(filtered_text, &self.ann_stack);
+            }
         } else {
             let mut tag_first = self.ann_stack.clone();
             let mut tag_cont = self.ann_stack.clone();
             tag_first.push(self.decorator.decorate_preformat_first());
             tag_cont.push(self.decorator.decorate_preformat_cont());
+            let tab_stop = self.options.tab_width.unwrap_or(8).max(1);
             self.wrapping.as_mut().unwrap().add_preformatted_text(
                 filtered_text,
+                tab_stop,
+                self.line_numbers.as_mut(),
                 &tag_first,
                 &tag_cont,
             );
@@ -1108,6 +2556,7 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
 
         self.flush_wrapping();
         let tag = self.ann_stack.clone();
+        let trim_blank = self.options.trim_blank_prefixes;
         self.lines.extend(
             other
                 .into_lines()
@@ -1115,6 +2564,11 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
                 .zip(prefixes)
                 .map(|(line, prefix)| match line {
                     RenderLine::Text(mut tline) => {
+                        let prefix = if trim_blank && tline.width() == 0 {
+                            prefix.trim_end()
+                        } else {
+                            prefix
+                        };
                         if !prefix.is_empty() {
                             tline.insert_front(TaggedString {
                                 s: prefix.to_string(),
@@ -1179,20 +2633,23 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
             })
             .collect::<Vec<(usize, Vec<RenderLine<_>>)>>();
 
-        tot_width += line_sets.len().saturating_sub(1);
+        // Each separator has the column gap either side of it.
+        let gap = self.options.column_gap;
+        tot_width += line_sets.len().saturating_sub(1) * (1 + 2 * gap);
 
-        let mut next_border = BorderHoriz::new(tot_width);
+        let style = self.options.border_style;
+        let mut next_border = BorderHoriz::new(tot_width).with_style(style);
 
         // Join the vertical lines to all the borders
-        {
+        if style != BorderStyle::None {
             let mut pos = 0;
             if let &mut RenderLine::Line(ref mut prev_border) = self.lines.back_mut().unwrap() {
                 html_trace!("Merging with last line:\n{}", prev_border.to_string());
                 for &(w, _) in &line_sets[..line_sets.len() - 1] {
                     html_trace!("pos={}, w={}", pos, w);
-                    prev_border.join_below(pos + w);
-                    next_border.join_above(pos + w);
-                    pos += w + 1;
+                    prev_border.join_below(pos + w + gap);
+                    next_border.join_above(pos + w + gap);
+                    pos += w + 1 + 2 * gap;
                 }
             } else {
                 panic!("Expected a border line");
@@ -1208,7 +2665,7 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
         let mut column_padding = vec![None; line_sets.len()];
 
         // If we're collapsing borders, do so.
-        if collapse {
+        if collapse && style != BorderStyle::None {
             html_trace!("Collapsing borders.");
             /* Collapse any top border */
             let mut pos = 0;
@@ -1240,7 +2697,7 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
                         unreachable!();
                     }
                 }
-                pos += w + 1;
+                pos += w + 1 + 2 * gap;
             }
 
             /* Collapse any bottom border */
@@ -1262,7 +2719,7 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
                         column_padding[col_no] = Some(line.to_vertical_lines_above())
                     }
                 }
-                pos += w + 1;
+                pos += w + 1 + 2 * gap;
             }
         }
 
@@ -1299,12 +2756,16 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
                     }));
                 }
                 if cellno != last_cellno {
-                    line.push_char('│', &self.ann_stack);
+                    let separator = format!("{0}{1}{0}", " ".repeat(gap), style.vertical());
+                    line.push_str(TaggedString {
+                        s: separator,
+                        tag: self.ann_stack.clone(),
+                    });
                 }
             }
             self.lines.push_back(RenderLine::Text(line));
         }
-        self.lines.push_back(RenderLine::Line(next_border));
+        self.add_horizontal_line(next_border);
     }
 
     fn append_vert_row<I>(&mut self, cols: I)
@@ -1357,37 +2818,65 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
 
     fn start_link(&mut self, target: &str) {
         let (s, annotation) = self.decorator.decorate_link_start(target);
-        self.ann_stack.push(annotation);
-        self.add_inline_text(&s);
+        self.push_annotation(annotation);
+        match self.options.link_style {
+            LinkStyle::Footnote => self.add_inline_text(&s),
+            LinkStyle::Inline => {
+                let s = self.decorator.decorate_inline_link_start(target);
+                self.link_targets.push(target.to_string());
+                self.add_inline_text(&s);
+            }
+            LinkStyle::None => (),
+        }
     }
     fn end_link(&mut self) {
         let s = self.decorator.decorate_link_end();
-        self.add_inline_text(&s);
-        self.ann_stack.pop();
+        match self.options.link_style {
+            LinkStyle::Footnote => self.add_inline_text(&s),
+            LinkStyle::Inline => {
+                let target = self.link_targets.pop().unwrap_or_default();
+                let s = self.decorator.decorate_inline_link_end(&target);
+                self.add_inline_text(&s);
+            }
+            LinkStyle::None => (),
+        }
+        self.pop_annotation();
+    }
+    fn add_citation(&mut self, target: &str, text: &str) {
+        if self.options.link_style == LinkStyle::Footnote {
+            let (s, annotation) = self.decorator.decorate_citation(target, text);
+            self.push_annotation(annotation);
+            self.add_inline_text(&s);
+            self.pop_annotation();
+        } else {
+            self.start_link(target);
+            self.add_inline_text(text);
+            self.end_link();
+        }
     }
     fn start_emphasis(&mut self) {
         let (s, annotation) = self.decorator.decorate_em_start();
-        self.ann_stack.push(annotation);
+        self.push_annotation(annotation);
         self.add_inline_text(&s);
     }
     fn end_emphasis(&mut self) {
         let s = self.decorator.decorate_em_end();
         self.add_inline_text(&s);
-        self.ann_stack.pop();
+        self.pop_annotation();
     }
     fn start_strong(&mut self) {
         let (s, annotation) = self.decorator.decorate_strong_start();
-        self.ann_stack.push(annotation);
+        self.push_annotation(annotation);
         self.add_inline_text(&s);
     }
     fn end_strong(&mut self) {
         let s = self.decorator.decorate_strong_end();
         self.add_inline_text(&s);
-        self.ann_stack.pop();
+        self.pop_annotation();
     }
     fn start_strikeout(&mut self) {
         let (s, annotation) = self.decorator.decorate_strikeout_start();
-        self.ann_stack.push(annotation);
+        self.push_annotation(annotation);
         self.add_inline_text(&s);
         self.text_filter_stack.push(filter_text_strikeout);
     }
@@ -1395,30 +2884,126 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
         self.text_filter_stack.pop().unwrap();
         let s = self.decorator.decorate_strikeout_end();
         self.add_inline_text(&s);
-        self.ann_stack.pop();
+        self.pop_annotation();
+    }
+    fn start_underline(&mut self) {
+        let (s, annotation) = self.decorator.decorate_underline_start();
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+    }
+    fn end_underline(&mut self) {
+        let s = self.decorator.decorate_underline_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
+    }
+    fn start_small(&mut self) {
+        let (s, annotation) = self.decorator.decorate_small_start();
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+    }
+    fn end_small(&mut self) {
+        let s = self.decorator.decorate_small_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
+    }
+    fn start_mark(&mut self) {
+        let (s, annotation) = self.decorator.decorate_mark_start();
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+        if let Some(delimiter) = self.options.mark_delimiter.clone() {
+            self.add_inline_text(&delimiter);
+        }
+    }
+    fn end_mark(&mut self) {
+        if let Some(delimiter) = self.options.mark_delimiter.clone() {
+            self.add_inline_text(&delimiter);
+        }
+        let s = self.decorator.decorate_mark_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
+    }
+    fn start_table_header(&mut self) {
+        let (s, annotation) = self.decorator.decorate_table_header_start();
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+    }
+    fn end_table_header(&mut self) {
+        let s = self.decorator.decorate_table_header_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
+    }
+    fn start_quote(&mut self, cite: Option<&str>) {
+        let (s, annotation) = self.decorator.decorate_quote_start(cite);
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+    }
+    fn end_quote(&mut self) {
+        let s = self.decorator.decorate_quote_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
     }
     fn start_color(&mut self, color:crate::Color){
         let (s, annotation) = self.decorator.decorate_color_start(color);
-        self.ann_stack.push(annotation);
+        self.push_annotation(annotation);
         self.add_inline_text(&s);
         // self.text_filter_stack.push(filter_text_color);
     }
     fn end_color(&mut self){
         let s = self.decorator.decorate_color_end();
         self.add_inline_text(&s);
-        self.ann_stack.pop();
+        self.pop_annotation();
+    }
+    fn start_bgcolor(&mut self, color: crate::Color) {
+        let (s, annotation) = self.decorator.decorate_bgcolor_start(color);
+        self.push_annotation(annotation);
+        self.add_inline_text(&s);
+    }
+    fn end_bgcolor(&mut self) {
+        let s = self.decorator.decorate_bgcolor_end();
+        self.add_inline_text(&s);
+        self.pop_annotation();
     }
     fn start_nobreak(&mut self) {
+        self.nobreak_depth += 1;
+        if self.nobreak_depth > 1 {
+            return;
+        }
         let (_, annotation) = self.decorator.mark_nobreak_start();
         self.flush_all(); // important! flush first before directly modify self.lines
         self.lines.push_back(RenderLine::Text(TaggedLine::from_string("".to_string(), &vec![annotation])));
         // self.add_inline_text(&s);
     }
     fn end_nobreak(&mut self) {
+        self.nobreak_depth -= 1;
+        if self.nobreak_depth > 0 {
+            return;
+        }
         let (_, annotation) = self.decorator.mark_nobreak_end();
         self.flush_all(); // important! flush first before directly modify self.lines
         self.lines.push_back(RenderLine::Text(TaggedLine::from_string("".to_string(), &vec![annotation])));
     }
+    fn start_nowrap(&mut self) {
+        self.nowrap_depth += 1;
+        if self.nowrap_depth > 1 || self.nobreak_depth > 0 {
+            return;
+        }
+        let (_, annotation) = self.decorator.mark_nobreak_start();
+        self.add_nowrap_marker(annotation);
+    }
+    fn end_nowrap(&mut self) {
+        self.nowrap_depth -= 1;
+        if self.nowrap_depth > 0 || self.nobreak_depth > 0 {
+            return;
+        }
+        let (_, annotation) = self.decorator.mark_nobreak_end();
+        self.add_nowrap_marker(annotation);
+    }
+    fn start_pre_wrap(&mut self) {
+        self.pre_wrap_depth += 1;
+    }
+    fn end_pre_wrap(&mut self) {
+        self.pre_wrap_depth -= 1;
+    }
     // fn start_annot(&mut self, ann: Self::Annotation) {
     //     self.ann_stack.push(ann);
     // }
@@ -1440,23 +3025,31 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
     }
     fn start_code(&mut self) {
         let (s, annotation) = self.decorator.decorate_code_start();
-        self.ann_stack.push(annotation);
+        self.push_annotation(annotation);
         self.add_inline_text(&s);
     }
     fn end_code(&mut self) {
         let s = self.decorator.decorate_code_end();
         self.add_inline_text(&s);
-        self.ann_stack.pop();
+        self.pop_annotation();
     }
-    fn add_image(&mut self, src: &str, title: &str, w:usize, h:usize) {
+    fn add_image(&mut self, src: &str, alt: Option<&str>, w:usize, h:usize) {
+        let title = alt.unwrap_or(crate::NO_ALT_TEXT);
         html_trace!("添加图片:{},{},{},{}",src,title,w,h);
-        html_trace!("{}",w*h);
+        html_trace!("{}",w.saturating_mul(h));
         let (s, tag) = self.decorator.decorate_image(src, title, w, h);
-        if w * h == 0{
+        let s = match alt {
+            None => self.options.image_placeholder.clone().unwrap_or(s),
+            Some(alt) => match self.options.image_format {
+                Some(ref format) => format.replace("{alt}", alt).replace("{src}", src),
+                None => s,
+            },
+        };
+        if w == 0 || h == 0 {
             html_trace!("添加图片替换文本:{},{},{},{}",src,title,w,h);
-            self.ann_stack.push(tag);
+            self.push_annotation(tag);
             self.add_inline_text(&s);
-            self.ann_stack.pop();
+            self.pop_annotation();
         } else {
             html_trace!("添加图片:{},{},{},{}",src,title,w,h);
             self.flush_all();
@@ -1465,11 +3058,19 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
     }
 
     fn add_asset(&mut self, typ: &str,value:Vec<String>){
-        if typ == "audio" {
-            assert!(!value.is_empty());
-            self.lines.push_back(RenderLine::Text(TaggedLine::from_string("".to_string(),&vec![self.decorator.custom("audio",value)])));
+        if (typ == "audio" || typ == "video") && !value.is_empty() {
+            if self.at_block_end {
+                self.start_block();
+            }
+            self.flush_all();
+            let text = self.decorator.media_placeholder(typ, &value[0]);
+            let annotation = self.decorator.custom(typ, value);
+            self.push_annotation(annotation);
+            self.add_inline_text(&text);
+            self.pop_annotation();
+            self.flush_all();
         } else {
-            html_trace!("sliently discard unknown resource type{}",typ);
+            html_trace!("sliently discard unknown or empty resource type{}",typ);
         }
     }
     fn header_prefix(&mut self, level: usize) -> String {
@@ -1477,17 +3078,37 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
     }
 
     fn quote_prefix(&mut self) -> String {
-        self.decorator.quote_prefix()
+        match self.options.quote_prefix {
+            Some(ref prefix) => prefix.clone(),
+            None => self.decorator.quote_prefix(),
+        }
     }
 
     fn unordered_item_prefix(&mut self) -> String {
-        self.decorator.unordered_item_prefix()
+        let bullets = &self.options.bullets;
+        if bullets.is_empty() {
+            self.decorator.unordered_item_prefix()
+        } else {
+            format!("{} ", bullets[self.list_depth % bullets.len()])
+        }
     }
 
     fn ordered_item_prefix(&mut self, i: i64) -> String {
         self.decorator.ordered_item_prefix(i)
     }
 
+    fn ordered_item_marker_prefix(&mut self, marker: &str) -> String {
+        self.decorator.ordered_item_marker_prefix(marker)
+    }
+
+    fn task_item_prefix(&mut self, checked: bool) -> String {
+        self.decorator.task_item_prefix(checked)
+    }
+
+    fn form_input(&mut self, kind: &str, checked: bool) -> String {
+        self.decorator.form_input(kind, checked)
+    }
+
     fn record_frag_start(&mut self, fragname: &str) {
         use self::TaggedLineElement::FragmentStart;
 
@@ -1497,9 +3118,320 @@ impl<D: TextDecorator> Renderer for SubRenderer<D> {
             .unwrap()
             .add_element(FragmentStart(fragname.to_string()));
     }
+
+    fn start_element(&mut self, name: &str) {
+        if self.options.element_annotations {
+            let annotation = self.decorator.decorate_element(name);
+            self.push_annotation(annotation);
+        }
+    }
+
+    fn end_element(&mut self) {
+        if self.options.element_annotations {
+            self.pop_annotation();
+        }
+    }
+
+    fn add_break_opportunity(&mut self) {
+        if self.pre_depth == 0 {
+            if let Some(wrapping) = self.wrapping.as_mut() {
+                wrapping.add_break_opportunity();
+            }
+        }
+    }
+}
+
+
+/// A decorator for use with `TextRenderer` which outputs plain UTF-8 text
+/// with no annotations.  Markup is rendered as text characters or footnotes.
+///
+/// Links to the same URL share a footnote:
+///
+/// ```rust
+/// let html = br#"<a href="http://x/">a</a> <a href="http://x/">b</a> <a href="http://y/">c</a>"#;
+/// assert_eq!(
+///     html2text::from_read(&html[..], 80),
+///     "[a][1] [b][1] [c][2]\n\n[1]: http://x/\n[2]: http://y/\n"
+/// );
+/// ```
+///
+/// With more than nine links, the URLs in the list still line up:
+///
+/// ```rust
+/// let html: String = (1..=12).map(|i| format!("<a href=\"http://x/{}\">{}</a> ", i, i)).collect();
+/// let text = html2text::from_read(html.as_bytes(), 100);
+/// assert!(text.contains("[9][9] [10][10] [11][11] [12][12]\n"));
+/// assert!(text.ends_with("[9]:  http://x/9\n[10]: http://x/10\n[11]: http://x/11\n[12]: http://x/12\n"));
+/// assert!(text.contains("\n[1]:  http://x/1\n"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PlainDecorator {
+    /// The footnote number given to each URL so far.
+    link_numbers: Rc<RefCell<HashMap<String, usize>>>,
+    /// The footnote number of the link being rendered.
+    cur_link: usize,
+}
+
+impl PlainDecorator {
+    /// Create a new `PlainDecorator`.
+    pub fn new() -> PlainDecorator {
+        Default::default()
+    }
+}
+
+impl TextDecorator for PlainDecorator {
+    type Annotation = ();
+
+    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation) {
+        let mut link_numbers = self.link_numbers.borrow_mut();
+        let next = link_numbers.len() + 1;
+        self.cur_link = *link_numbers.entry(url.to_string()).or_insert(next);
+        ("[".to_string(), ())
+    }
+
+    fn decorate_link_end(&mut self) -> String {
+        format!("][{}]", self.cur_link)
+    }
+
+    fn decorate_citation(&mut self, url: &str, _text: &str) -> (String, Self::Annotation) {
+        self.decorate_link_start(url);
+        (format!("[{}]", self.cur_link), ())
+    }
+
+    fn decorate_em_start(&mut self) -> (String, Self::Annotation) {
+        ("*".to_string(), ())
+    }
+
+    fn decorate_em_end(&mut self) -> String {
+        "*".to_string()
+    }
+
+    fn decorate_strong_start(&mut self) -> (String, Self::Annotation) {
+        ("**".to_string(), ())
+    }
+
+    fn decorate_strong_end(&mut self) -> String {
+        "**".to_string()
+    }
+
+    fn decorate_strikeout_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn decorate_strikeout_end(&mut self) -> String {
+        "".to_string()
+    }
+
+    fn decorate_code_start(&mut self) -> (String, Self::Annotation) {
+        ("`".to_string(), ())
+    }
+
+    fn decorate_code_end(&mut self) -> String {
+        "`".to_string()
+    }
+
+    fn decorate_color_start(&mut self, _color: crate::Color) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn decorate_color_end(&mut self) -> String {
+        "".to_string()
+    }
+
+    fn mark_nobreak_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn mark_nobreak_end(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn decorate_preformat_first(&mut self) -> Self::Annotation {}
+
+    fn decorate_preformat_cont(&mut self) -> Self::Annotation {}
+
+    fn decorate_image(&mut self, _src: &str, title: &str, _w: usize, _h: usize) -> (String, Self::Annotation) {
+        (format!("[{}]", title), ())
+    }
+
+    fn custom(&mut self, _typ: &str, _value: Vec<String>) -> Self::Annotation {}
+
+    fn header_prefix(&mut self, level: usize) -> String {
+        "#".repeat(level) + " "
+    }
+
+    fn quote_prefix(&mut self) -> String {
+        "> ".to_string()
+    }
+
+    fn unordered_item_prefix(&mut self) -> String {
+        "* ".to_string()
+    }
+
+    fn ordered_item_prefix(&mut self, i: i64) -> String {
+        format!("{}. ", i)
+    }
+
+    fn finalise(&mut self, links: Vec<LinkRef>) -> Vec<TaggedLine<()>> {
+        // Only list each URL once, in the order they were numbered.
+        let mut seen = HashSet::new();
+        let links: Vec<_> = links
+            .into_iter()
+            .filter(|link| seen.insert(link.url.clone()))
+            .collect();
+        // Pad the numbers so that the URLs line up.
+        let width = format!("[{}]:", links.len()).len();
+        links
+            .into_iter()
+            .enumerate()
+            .map(|(idx, link)| {
+                let marker = format!("{:<width$}", format!("[{}]:", idx + 1), width = width);
+                let line = match link.title {
+                    Some(title) => format!("{} {} \"{}\"", marker, link.url, title),
+                    None => format!("{} {}", marker, link.url),
+                };
+                TaggedLine::from_string(line, &())
+            })
+            .collect()
+    }
+
+    fn make_subblock_decorator(&self) -> Self {
+        PlainDecorator {
+            link_numbers: self.link_numbers.clone(),
+            cur_link: 0,
+        }
+    }
 }
 
+/// A decorator which marks up the text as Markdown, with links inline as
+/// `[text](url)`.  Tables become pipe tables, with the first row as the
+/// header, and each cell's content joined onto one line.
+///
+/// ```rust
+/// let html = b"<table><tr><th>a</th><th>b</th></tr>\
+///              <tr><td>1</td><td>x | y</td></tr>\
+///              <tr><td colspan=\"2\">wide</td></tr></table>";
+/// assert_eq!(
+///     html2text::config::markdown().convert(&html[..]),
+///     "| a | b |\n|---|---|\n| 1 | x \\| y |\n| wide |  |\n"
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownDecorator {
+    /// The targets of the links currently open.
+    link_targets: Vec<String>,
+}
+
+impl MarkdownDecorator {
+    /// Create a new `MarkdownDecorator`.
+    pub fn new() -> MarkdownDecorator {
+        Default::default()
+    }
+}
+
+impl TextDecorator for MarkdownDecorator {
+    type Annotation = ();
+
+    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation) {
+        self.link_targets.push(url.to_string());
+        ("[".to_string(), ())
+    }
+
+    fn decorate_link_end(&mut self) -> String {
+        format!("]({})", self.link_targets.pop().unwrap_or_default())
+    }
+
+    fn decorate_em_start(&mut self) -> (String, Self::Annotation) {
+        ("*".to_string(), ())
+    }
+
+    fn decorate_em_end(&mut self) -> String {
+        "*".to_string()
+    }
+
+    fn decorate_strong_start(&mut self) -> (String, Self::Annotation) {
+        ("**".to_string(), ())
+    }
+
+    fn decorate_strong_end(&mut self) -> String {
+        "**".to_string()
+    }
 
+    fn decorate_strikeout_start(&mut self) -> (String, Self::Annotation) {
+        ("~~".to_string(), ())
+    }
+
+    fn decorate_strikeout_end(&mut self) -> String {
+        "~~".to_string()
+    }
+
+    fn decorate_code_start(&mut self) -> (String, Self::Annotation) {
+        ("`".to_string(), ())
+    }
+
+    fn decorate_code_end(&mut self) -> String {
+        "`".to_string()
+    }
+
+    fn decorate_color_start(&mut self, _color: crate::Color) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn decorate_color_end(&mut self) -> String {
+        "".to_string()
+    }
+
+    fn mark_nobreak_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn mark_nobreak_end(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), ())
+    }
+
+    fn decorate_preformat_first(&mut self) -> Self::Annotation {}
+
+    fn decorate_preformat_cont(&mut self) -> Self::Annotation {}
+
+    fn decorate_image(&mut self, src: &str, title: &str, _w: usize, _h: usize) -> (String, Self::Annotation) {
+        (format!("![{}]({})", title, src), ())
+    }
+
+    fn custom(&mut self, _typ: &str, _value: Vec<String>) -> Self::Annotation {}
+
+    fn horizontal_rule(&mut self) -> Option<String> {
+        Some("---".to_string())
+    }
+
+    fn pipe_tables(&self) -> bool {
+        true
+    }
+
+    fn header_prefix(&mut self, level: usize) -> String {
+        "#".repeat(level) + " "
+    }
+
+    fn quote_prefix(&mut self) -> String {
+        "> ".to_string()
+    }
+
+    fn unordered_item_prefix(&mut self) -> String {
+        "- ".to_string()
+    }
+
+    fn ordered_item_prefix(&mut self, i: i64) -> String {
+        format!("{}. ", i)
+    }
+
+    fn finalise(&mut self, _links: Vec<LinkRef>) -> Vec<TaggedLine<()>> {
+        // The links are all inline.
+        Vec::new()
+    }
+
+    fn make_subblock_decorator(&self) -> Self {
+        MarkdownDecorator::new()
+    }
+}
 
 /// A decorator to generate rich text (styled) rather than
 /// pure text output.
@@ -1522,8 +3454,27 @@ pub enum RichAnnotation {
     Strong,
     /// Stikeout text
     Strikeout,
+    /// Underlined text, from `<u>` or a `text-decoration: underline` style.
+    ///
+    /// ```rust
+    /// use html2text::render::text_renderer::{RichAnnotation, TaggedLineElement};
+    /// let html = b"<span style=\"text-decoration: underline\">hi</span>";
+    /// let lines = html2text::from_read_rich(&html[..], 80);
+    /// match lines[0].iter().next() {
+    ///     Some(TaggedLineElement::Str(ts)) => {
+    ///         assert_eq!(ts.s, "hi");
+    ///         assert_eq!(ts.tag, vec![RichAnnotation::Underline]);
+    ///     }
+    ///     other => panic!("unexpected {:?}", other),
+    /// };
+    /// ```
+    Underline,
+    /// De-emphasised (`<small>`) text, which might be rendered dim.
+    Small,
     /// Colored text
     Colored(crate::Color),
+    /// Text with a background colour, e.g. highlighted (`<mark>`) text.
+    BgColored(crate::Color),
     /// Code
     Code,
     /// Preformatted; true if a continuation line for an overly-long line.
@@ -1532,6 +3483,9 @@ pub enum RichAnnotation {
     NoBreakBegin,
     /// end
     NoBreakEnd,
+    /// Text from an HTML element with this name, such as `"h1"` or
+    /// `"li"`, if element annotations are enabled.
+    Element(String),
     /// Bell
     Bell,
     /// Redact
@@ -1591,6 +3545,22 @@ impl TextDecorator for RichDecorator {
         "".to_string()
     }
 
+    fn decorate_underline_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), RichAnnotation::Underline)
+    }
+
+    fn decorate_underline_end(&mut self) -> String {
+        "".to_string()
+    }
+
+    fn decorate_small_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), RichAnnotation::Small)
+    }
+
+    fn decorate_table_header_start(&mut self) -> (String, Self::Annotation) {
+        ("".to_string(), RichAnnotation::Strong)
+    }
+
     fn decorate_code_start(&mut self) -> (String, Self::Annotation) {
         ("`".to_string(), RichAnnotation::Code)
     }
@@ -1627,7 +3597,7 @@ impl TextDecorator for RichDecorator {
         format!("{}. ", i)
     }
 
-    fn finalise(&mut self, _links: Vec<String>) -> Vec<TaggedLine<RichAnnotation>> {
+    fn finalise(&mut self, _links: Vec<LinkRef>) -> Vec<TaggedLine<RichAnnotation>> {
         Vec::new()
     }
 
@@ -1643,6 +3613,10 @@ impl TextDecorator for RichDecorator {
         "".to_string()
     }
 
+    fn decorate_bgcolor_start(&mut self, color: crate::Color) -> (String, Self::Annotation) {
+        ("".to_string(), RichAnnotation::BgColored(color))
+    }
+
     fn mark_nobreak_start(&mut self) -> (String, Self::Annotation) {
         ("".to_string(), RichAnnotation::NoBreakBegin)
     }
@@ -1660,5 +3634,9 @@ impl TextDecorator for RichDecorator {
     fn custom(&mut self, typ: &str,value: Vec<String>) -> Self::Annotation {
         RichAnnotation::Custom(typ.to_string(),value)
     }
+
+    fn decorate_element(&mut self, name: &str) -> Self::Annotation {
+        RichAnnotation::Element(name.to_string())
+    }
     
 }